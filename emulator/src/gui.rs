@@ -2,7 +2,8 @@ use nwg::NativeUi;
 use nwd::NwgUi;
 use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
 use std::cell::RefCell;
-use crate::{ToSysMessage, DEFAULT_STEP_WAIT};
+use crate::{ToSysMessage, InputCommand, DEFAULT_STEP_WAIT};
+use crate::system::Key;
 
 pub enum ToGuiMessage {
     PortB(u8),
@@ -163,6 +164,9 @@ pub struct EmulatorGui {
 
     #[nwg_control(parent: tabs_container, text: "LCD")]
     tab_lcd: nwg::Tab,
+
+    #[nwg_control(parent: tabs_container, text: "Inputs")]
+    tab_inputs: nwg::Tab,
     
     #[nwg_resource(source_bin: Some(include_bytes!("../imgs/led_on.bmp")))]
     led_on_bmp: nwg::Bitmap,
@@ -276,6 +280,31 @@ pub struct EmulatorGui {
         "╔════════════════╗\n║This screen     ║\n║    is disabled.║\n╚════════════════╝")]
     #[nwg_layout_item(layout: lcd_grid, row: 0, col: 0)] 
     lcd_screen_lbl: nwg::Label,
+
+    #[nwg_layout(parent: tab_inputs, spacing: 1)]
+    inputs_grid: nwg::GridLayout,
+
+    #[nwg_control(parent: tab_inputs, text: "press 0")]
+    #[nwg_layout_item(layout: inputs_grid, row: 0, col: 0, col_span: 3)]
+    input_cmd_tinput: nwg::TextInput,
+
+    #[nwg_control(parent: tab_inputs, text: "Send")]
+    #[nwg_layout_item(layout: inputs_grid, row: 0, col: 3)]
+    #[nwg_events( OnButtonClick: [EmulatorGui::send_input] )]
+    input_cmd_button: nwg::Button,
+
+    #[nwg_control(parent: tab_inputs, font: Some(&data.segoe_small), text: 
+        "sw <n> on|off: flip the DIP switch SW.n\npress <n> / release <n>: push-button BTN.n\nturn <detents>: rotary encoder, negative to turn counterclockwise\n\
+        key <name>, keydown <name>, keyup <name>, type <text>: PS/2 keyboard")]
+    #[nwg_layout_item(layout: inputs_grid, row: 1, col: 0, row_span: 2, col_span: 4)]
+    input_help_lbl: nwg::Label,
+
+    // Focused, it forwards the keys pressed and released to the PS/2 keyboard
+    #[nwg_control(parent: tab_inputs, readonly: true, text: "Click here to type on the PS/2 keyboard")]
+    #[nwg_layout_item(layout: inputs_grid, row: 3, col: 0, col_span: 4)]
+    #[nwg_events( OnKeyPress: [EmulatorGui::send_key_down(SELF, EVT_DATA)],
+        OnKeyRelease: [EmulatorGui::send_key_up(SELF, EVT_DATA)] )]
+    keyboard_tinput: nwg::TextInput,
 }
 
 impl EmulatorGui {
@@ -379,6 +408,8 @@ impl EmulatorGui {
                     self.step_wait_time_tb.set_enabled(false);
                     self.print_log_cbox.set_enabled(false);
                     self.port_a_breakpoint_cbox.set_enabled(false);
+                    self.input_cmd_button.set_enabled(false);
+                    self.keyboard_tinput.set_enabled(false);
 
                     nwg::modal_info_message(&self.window, "CPU stopped", 
                         "The CPU is done executing the program.\nClose the main window to exit."
@@ -480,6 +511,25 @@ impl EmulatorGui {
         }));
     }
 
+    fn send_input(&self) {
+        match InputCommand::parse(&self.input_cmd_tinput.text()) {
+            Ok(cmd) => self.send_gui_msg(ToSysMessage::Input(cmd)),
+            Err(err) => { nwg::modal_error_message(&self.window, "Invalid input", &err); },
+        }
+    }
+
+    fn send_key_down(&self, data: &nwg::EventData) {
+        if let Some(key) = Key::for_virtual_key(data.on_key()) {
+            self.send_gui_msg(ToSysMessage::Input(InputCommand::KeyDown(key)));
+        }
+    }
+
+    fn send_key_up(&self, data: &nwg::EventData) {
+        if let Some(key) = Key::for_virtual_key(data.on_key()) {
+            self.send_gui_msg(ToSysMessage::Input(InputCommand::KeyUp(key)));
+        }
+    }
+
     fn send_gui_msg(&self, msg: ToSysMessage) {
         self.channels.tx.send(msg).expect("SYS thread has hung up");
     }
@@ -16,20 +16,36 @@ macro_rules! log {
 
 pub enum LogMessage {
     Log(String),
+    // Decoded operation received by the LCD, for the LCD instruction log
+    LcdOperation(String),
     ChangePrintLog(bool),
     Exit,
 }
 
 pub struct Logger {
     log_file: Option<File>,
+    lcd_log_file: Option<File>,
+    // In bytes, None if the log file can grow without limit
+    log_max_size: Option<usize>,
+    log_size: usize,
+    lcd_log_size: usize,
     print_log: bool,
     rx_log_msgs: Receiver<LogMessage>,
 }
 
 impl Logger {
-    pub fn new(log_file: Option<File>, rx_log_msgs: Receiver<LogMessage>) -> Logger {
+    pub fn new(
+        log_file: Option<File>,
+        lcd_log_file: Option<File>,
+        log_max_size: Option<usize>,
+        rx_log_msgs: Receiver<LogMessage>
+    ) -> Logger {
         Logger {
             log_file,
+            lcd_log_file,
+            log_max_size,
+            log_size: 0,
+            lcd_log_size: 0,
             print_log: false,
             rx_log_msgs,
         }
@@ -46,11 +62,10 @@ impl Logger {
                             print!("{}", msg);
                         }
 
-                        if let Some(log_file) = &mut self.log_file {
-                            log_file.write(msg.as_bytes())
-                                .expect("Failed to write log");
-                        }
+                        write_capped(&mut self.log_file, &mut self.log_size, self.log_max_size, &msg);
                     },
+                    LogMessage::LcdOperation(msg) => write_capped(&mut self.lcd_log_file, &mut self.lcd_log_size, 
+                        self.log_max_size, &format!("{}\n", msg)),
                     LogMessage::ChangePrintLog(print_log) => self.print_log = print_log,
                     LogMessage::Exit => break 'logger_thread_main,
                 }
@@ -58,3 +73,20 @@ impl Logger {
         }).unwrap()
     }
 }
+
+// Append the message to the file, unless it would make it go over the size limit:
+// the file is then closed with a note, and the following messages are dropped
+fn write_capped(file: &mut Option<File>, size: &mut usize, max_size: Option<usize>, msg: &str) {
+    if let Some(log_file) = file {
+        *size += msg.len();
+
+        match max_size {
+            Some(max_size) if *size > max_size => {
+                log_file.write_all(b"\n\nLog size limit reached, the rest of the log is dropped")
+                    .expect("Failed to write log");
+                *file = None;
+            },
+            _ => log_file.write_all(msg.as_bytes()).expect("Failed to write log"),
+        }
+    }
+}
@@ -4,14 +4,14 @@ extern crate native_windows_derive as nwd;
 extern crate chrono;
 #[macro_use]
 extern crate clap;
-extern crate timer;
 extern crate spin_sleep;
 extern crate rand;
 use chrono::prelude::*;
-use std::path::Path;
-use std::fs::{File, OpenOptions};  
-use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::fs::{self, File, OpenOptions};  
+use std::io::{self, BufRead, Read, Write};
 use std::sync::mpsc::{self, Sender};
+use std::thread;
 
 #[macro_use]
 pub mod logger;
@@ -19,12 +19,26 @@ pub mod system;
 pub mod gui;
 
 use logger::{Logger, LogMessage};
-use system::{DEFAULT_STEP_WAIT, ToSysMessage, PhysSystem};
+use system::{DEFAULT_STEP_WAIT, DEFAULT_BOUNCE_TIME, ToSysMessage, PhysSystem, Wiring, Geometry, CharRom, InputCommand, Timeline, HostPort};
 use gui::ToGuiMessage;
 
 pub struct Config {
     pub lcd_enabled: bool,
+    pub lcd_operation_log: bool,
     pub allow_garbage: bool,
+    pub crash_dump_dir: Option<PathBuf>,
+    pub dump_on_stp: bool,
+    pub via_addrs: Vec<u16>,
+    pub wiring: Wiring,
+    pub lcd_screenshot: Option<PathBuf>,
+    pub lcd_geometry: Geometry,
+    pub lcd_char_rom: CharRom,
+    pub clock_freq: usize,
+    pub input_bounce_time: usize,
+    pub timeline: Timeline,
+    pub acia_addr: Option<u16>,
+    pub acia_port: HostPort,
+    pub acia_xmit_bug: bool,
 }
 
 fn main() {
@@ -34,6 +48,24 @@ fn main() {
         (about: "Emulate a physical w65c02s system to run, test and debug assembly programs")
         (@arg INPUT: +required "Sets the input file to use")
         (@arg log_dir_path: -l --log +takes_value "Save the logs in a file. Takes a path to the folder the log will be put in")
+        (@arg log_max_size: --logmax +takes_value "Maximum size of the log file and of the LCD log file in MiB, further messages are dropped")
+        (@arg lcd_log_file: --lcdlog +takes_value "Write every operation received by the LCD in that file, decoded, with the cycle it arrived on")
+        (@arg crash_dump_dir: -c --crashdump +takes_value "Write a crash dump in that folder when the CPU reads garbage or executes a BRK (which also stops it)")
+        (@arg dump_on_stp: --dumponstp "Also write a crash dump when the CPU executes an STP")
+        (@arg via_addrs: --via +takes_value +multiple number_of_values(1) "Map a 6522 at that hex address, can be used several times (the first one is connected to the LCD and LEDs). Defaults to 6000")
+        (@arg wiring_file: -w --wiring +takes_value "Connect the VIA pins as described in that file instead of the default breadboard wiring")
+        (@arg lcd_screenshot: -s --screenshot +takes_value "Save a dot by dot picture of the LCD in that file when the emulator exits, as PNG, PPM or text made of half blocks depending on the extension")
+        (@arg lcd_size: --lcdsize +takes_value "Size of the LCD module in characters: 16x1, 16x2, 20x2, 20x4 or 40x2. Defaults to 16x2")
+        (@arg char_rom: --charrom +takes_value "Character ROM of the LCD: A00 (Japanese), A02 (European) or a custom table file. Defaults to A00")
+        (@arg clock_freq: --clock +takes_value "Frequency of the emulated CPU clock in Hz, used to time the peripherals. Defaults to 1000000")
+        (@arg input_cmds: --input +takes_value +multiple number_of_values(1) "Act on the switches, buttons and encoder of the wiring file before starting, e.g. \"sw 3 on\". Can be used several times")
+        (@arg console_inputs: -i --inputs "Read input commands (sw <n> on|off, press <n>, release <n>, turn <detents>, key <name>, type <text>...) from the console while running")
+        (@arg bounce_time: --bounce +takes_value "Time the push-buttons bounce for when pressed or released, in µs (0 for perfect buttons). Defaults to 2000")
+        (@arg acia_addr: --acia +takes_value "Map a 6551 ACIA at that hex address, its serial line going to the host (see --serial)")
+        (@arg acia_port: --serial +takes_value "Host end of the ACIA serial line: stdio, pty (Unix only) or tcp:<port> on localhost. Defaults to stdio")
+        (@arg acia_xmit_bug: --aciabug "Emulate the transmitter bug of the WDC W65C51N: TDRE always reads 1 and there is no transmit interrupt")
+        (@arg timeline_file: -t --timeline +takes_value "Apply the input changes of that timeline file, at given cycle counts or when the CPU reaches given addresses")
+        (@arg headless: --headless "Run at full speed without the GUI, until the CPU stops (e.g. with a stop in the timeline)")
         (@arg disable_lcd: -d --disablelcd "Disable the LCD screen")
         (@arg allow_garbage: --allowgarbage "Don't panic when the CPU or VIA are reading garbage, send a log message instead")
     ).get_matches();
@@ -69,14 +101,130 @@ fn main() {
         None
     };
 
+    let lcd_log_file = matches.value_of("lcd_log_file").map(|lcd_log_file| File::create(lcd_log_file)
+        .expect("Unable to create LCD log file")
+    );
+
+    let log_max_size = matches.value_of("log_max_size").map(|size| size
+        .parse::<usize>()
+        .expect("Invalid maximum log size (expected a number of MiB)") * 1024 * 1024
+    );
+
+    let crash_dump_dir = matches.value_of("crash_dump_dir").map(|crash_dump_dir| {
+        let crash_dump_dir = PathBuf::from(crash_dump_dir);
+        assert!(crash_dump_dir.is_dir(), 
+            "Invalid directory path: {}", 
+            crash_dump_dir.display()
+        );
+        crash_dump_dir
+    });
+
+    let via_addrs: Vec<u16> = match matches.values_of("via_addrs") {
+        Some(addrs) => addrs.map(|addr| {
+            let addr = u16::from_str_radix(addr.trim_start_matches('$'), 16)
+                .expect("Invalid VIA address (expected a hexadecimal number)");
+            // The VIAs must sit between the RAM and the ROM, on 16 registers boundaries
+            assert!((0x4000..=0x7ff0).contains(&addr) && addr & 0x000f == 0,
+                "Invalid VIA address: {:04x} (must be aligned on $10 (16 registers) between 4000 and 7ff0)", addr
+            );
+            addr
+        }).collect(),
+        None => vec![0x6000],
+    };
+    for (i, addr) in via_addrs.iter().enumerate() {
+        assert!(!via_addrs[..i].contains(addr), "Two VIAs are mapped to {:04x}", addr);
+    }
+
+    let wiring = match matches.value_of("wiring_file") {
+        Some(wiring_file) => {
+            let wiring = fs::read_to_string(wiring_file)
+                .expect("Failed to read wiring file (make sure you typed the name properly)");
+            Wiring::parse(&wiring).unwrap_or_else(|err| panic!("Invalid wiring file, {}", err))
+        },
+        None => Wiring::default_for(via_addrs[0]),
+    };
+    for addr in wiring.via_addrs() {
+        assert!(via_addrs.contains(&addr), "The wiring file connects a VIA at {:04x}, which isn't mapped (see --via)", addr);
+    }
+
+    let lcd_geometry = matches.value_of("lcd_size").map_or(Geometry::default(), |size| 
+        Geometry::parse(size).expect("Invalid LCD size (expected 16x1, 16x2, 20x2, 20x4 or 40x2)")
+    );
+
+    let lcd_char_rom = match matches.value_of("char_rom") {
+        Some(rom) if rom.eq_ignore_ascii_case("A00") => CharRom::a00(),
+        Some(rom) if rom.eq_ignore_ascii_case("A02") => CharRom::a02(),
+        Some(rom_file) => {
+            let rom = fs::read_to_string(rom_file)
+                .expect("Failed to read character ROM file (make sure you typed the name properly)");
+            CharRom::parse(&rom).unwrap_or_else(|err| panic!("Invalid character ROM file, {}", err))
+        },
+        None => CharRom::a00(),
+    };
+
+    let clock_freq = matches.value_of("clock_freq").map_or(1_000_000, |freq| freq
+        .parse::<usize>()
+        .expect("Invalid clock frequency (expected a number of Hz)")
+    );
+
+    let input_bounce_time = matches.value_of("bounce_time").map_or(DEFAULT_BOUNCE_TIME, |time| time
+        .parse::<usize>()
+        .expect("Invalid bounce time (expected a number of µs)")
+    );
+
+    let input_cmds: Vec<InputCommand> = matches.values_of("input_cmds").map_or(Vec::new(), |cmds| cmds
+        .map(|cmd| InputCommand::parse(cmd).unwrap_or_else(|err| panic!("Invalid input, {}", err)))
+        .collect()
+    );
+
+    let acia_addr = matches.value_of("acia_addr").map(|addr| {
+        let addr = u16::from_str_radix(addr.trim_start_matches('$'), 16)
+            .expect("Invalid ACIA address (expected a hexadecimal number)");
+        assert!((0x4000..=0x7ffc).contains(&addr) && addr & 0x0003 == 0,
+            "Invalid ACIA address: {:04x} (must be a multiple of 4 between 4000 and 7ffc)", addr
+        );
+        assert!(!via_addrs.contains(&(addr & 0xfff0)), "The ACIA and a VIA are both mapped to {:04x}", addr);
+        addr
+    });
+
+    let acia_port = matches.value_of("acia_port").map_or(HostPort::Stdio, |port| 
+        HostPort::parse(port).expect("Invalid serial port (expected stdio, pty or tcp:<port>)")
+    );
+    assert!(!(acia_addr.is_some() && acia_port == HostPort::Stdio && matches.is_present("console_inputs")),
+        "The ACIA and the input commands can't both use the console, see --serial"
+    );
+
+    let timeline = matches.value_of("timeline_file").map_or(Timeline::default(), |timeline_file| {
+        let timeline = fs::read_to_string(timeline_file)
+            .expect("Failed to read timeline file (make sure you typed the name properly)");
+        Timeline::parse(&timeline).unwrap_or_else(|err| panic!("Invalid timeline file, {}", err))
+    });
+    for addr in timeline.via_addrs() {
+        assert!(via_addrs.contains(&addr), "The timeline drives a VIA at {:04x}, which isn't mapped (see --via)", addr);
+    }
+
     let config = Config {
         lcd_enabled: if matches.is_present("disable_lcd") { false } else { true },
+        lcd_operation_log: matches.is_present("lcd_log_file"),
         allow_garbage: if matches.is_present("allow_garbage") { true } else { false },
+        crash_dump_dir,
+        dump_on_stp: matches.is_present("dump_on_stp"),
+        via_addrs,
+        wiring,
+        lcd_screenshot: matches.value_of("lcd_screenshot").map(PathBuf::from),
+        lcd_geometry,
+        lcd_char_rom,
+        clock_freq,
+        input_bounce_time,
+        timeline,
+        acia_addr,
+        acia_port,
+        acia_xmit_bug: matches.is_present("acia_xmit_bug"),
     };
 
     let (tx_log_msgs, rx_log_msgs) = mpsc::channel();
 
-    let logger = Logger::new(log_file, rx_log_msgs);
+    let logger = Logger::new(log_file, lcd_log_file, log_max_size, rx_log_msgs);
     let logger_handle = logger.run();
 
     let (tx_sys_msgs, rx_sys_msgs) = mpsc::channel();
@@ -86,12 +234,33 @@ fn main() {
         Sender::clone(&tx_log_msgs), tx_gui_msgs, rx_sys_msgs);
     let system_handle = system.run();
 
-    gui::run(tx_sys_msgs, rx_gui_msgs, String::from(bin_path
-        .file_name()
-        .unwrap()
-        .to_str()
-        .unwrap()
-    ));
+    for cmd in input_cmds {
+        tx_sys_msgs.send(ToSysMessage::Input(cmd)).expect("SYS thread has hung up");
+    }
+    if matches.is_present("console_inputs") {
+        read_console_inputs(Sender::clone(&tx_sys_msgs));
+    }
+
+    if matches.is_present("headless") {
+        // Same stimulus and results on every run: no breakpoint and no waiting between steps
+        tx_sys_msgs.send(ToSysMessage::Breakpoint(false)).expect("SYS thread has hung up");
+        tx_sys_msgs.send(ToSysMessage::ChangeWaitTime(0)).expect("SYS thread has hung up");
+        tx_sys_msgs.send(ToSysMessage::Run).expect("SYS thread has hung up");
+
+        // The GUI messages are dropped, but the channel is kept open until the SYS thread ends
+        for msg in rx_gui_msgs.iter() {
+            if let ToGuiMessage::Stopped = msg {
+                break;
+            }
+        }
+    } else {
+        gui::run(tx_sys_msgs, rx_gui_msgs, String::from(bin_path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+        ));
+    }
 
     print!("Waiting for SYS thread to end... ");
     system_handle.join().unwrap();
@@ -102,3 +271,23 @@ fn main() {
     logger_handle.join().unwrap();
     println!("logger thread ended");
 }
+
+// Forward the input commands typed in the console to the SYS thread, until the console is closed
+fn read_console_inputs(tx_sys_msgs: Sender<ToSysMessage>) {
+    thread::Builder::new().name("console inputs thread".to_string()).spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = line.expect("Failed to read the console");
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match InputCommand::parse(&line) {
+                Ok(cmd) => if tx_sys_msgs.send(ToSysMessage::Input(cmd)).is_err() {
+                    // The SYS thread is done
+                    break;
+                },
+                Err(err) => println!("Invalid input, {}", err),
+            }
+        }
+    }).unwrap();
+}
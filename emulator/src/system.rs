@@ -1,15 +1,45 @@
 use w65c02s::{System, W65C02S, State};
-use std::{thread::{self, JoinHandle}, time};
+use std::{thread, time};
 use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::panic::{self, AssertUnwindSafe};
+use std::fs::File;
+use std::io::Write;
+use chrono::prelude::*;
 use crate::{Config, LogMessage, ToGuiMessage};
 
 mod lcd;
 mod via;
-use lcd::{LCD, SysToLcdMessage};
+mod history;
+mod irq;
+mod wiring;
+mod inputs;
+mod timeline;
+mod acia;
+mod serial;
+mod keyboard;
+use lcd::LCD;
+pub use lcd::{Geometry, CharRom, LcdState};
+use history::{InstrHistory, InstrRecord};
+use irq::IrqLine;
+use wiring::{ViaPin, PeriphPin, LcdPin, Pull, Net};
+pub use wiring::Wiring;
+use inputs::Inputs;
+pub use inputs::{InputCommand, DEFAULT_BOUNCE_TIME};
+pub use timeline::Timeline;
+use timeline::Action;
+use acia::W65C51;
+pub use serial::HostPort;
+use serial::SerialBridge;
+pub use keyboard::Key;
 
 // Default waiting time between steps when running, in milliseconds
 pub const DEFAULT_STEP_WAIT: usize = 50;
 
+// Number of CPU cycles a peripheral waits for a time in µs, rounded up
+fn us_to_cycles(time: usize, clock_freq: usize) -> usize {
+    (time * clock_freq).div_ceil(1_000_000)
+}
+
 const OPCODES: [&str; 256] = [
     "BRK",     "ORA i_zp_x", "<invalid: NOP imm>", "<invalid: NOP>", "TSB zp",              "ORA zp",    "ASL zp",    "RMB0 zp", 
     "PHP",     "ORA imm",    "ASL",                "<invalid: NOP>", "TSB abs",             "ORA abs",   "ASL abs",   "BBR0 zp_rel",
@@ -52,6 +82,7 @@ pub enum ToSysMessage {
     ChangeWaitTime(usize),
     ShowLog(bool),
     Breakpoint(bool),
+    Input(InputCommand),
     Exit,
 }
 
@@ -95,15 +126,70 @@ impl<T: Clone + Copy> Data<T> {
     }
 }
 
-pub struct PhysSystem {
-    prgm_config: Config,
-    mem: [Data<u8>; 65_536],
+/// Something driving a net, as opposed to the inputs and resistors connected to it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Driver {
+    Via,
+    Lcd,
+    Input,
+    Timeline,
+}
+
+/// A 6522 mapped to `base_addr`..=`base_addr + 0xf`, along with the state of its ports.
+pub struct ViaSlot {
+    base_addr: u16,
     via: via::W65C22S,
-    via_pb: u8,
+    pb: u8,
     pb_changed: bool,
-    via_pa: u8,
+    pa: u8,
     pa_changed: bool,
-    irq: bool,
+    irq_source: usize,
+    // What each pin is connected to, indexed by ViaPin::index
+    nets: Vec<Net>,
+    // Pins driven by several outputs at once, one bit per ViaPin::index
+    contended_pins: u32,
+    // Floating inputs already flagged, until they are driven again, one bit per ViaPin::index
+    floating_warned: u32,
+    // Pins driven by the timeline and their levels, one bit per ViaPin::index
+    forced_pins: u32,
+    forced_levels: u32,
+}
+
+impl ViaSlot {
+    fn new(base_addr: u16, wiring: &Wiring, irq_line: &mut IrqLine) -> ViaSlot {
+        ViaSlot {
+            base_addr,
+            via: via::W65C22S::new(),
+            pb: rand::random(),
+            pb_changed: false,
+            pa: rand::random(),
+            pa_changed: false,
+            irq_source: irq_line.add_source(&format!("VIA ${:04x}", base_addr)),
+            nets: wiring.nets_of(base_addr),
+            contended_pins: 0,
+            floating_warned: 0,
+            forced_pins: 0,
+            forced_levels: 0,
+        }
+    }
+}
+
+/// A 6551 mapped to `base_addr`..=`base_addr + 0x3`, along with the host end of its serial line.
+pub struct AciaSlot {
+    base_addr: u16,
+    acia: W65C51,
+    irq_source: usize,
+    bridge: SerialBridge,
+}
+
+pub struct PhysSystem {
+    prgm_config: Config,
+    mem: [Data<u8>; 65_536],
+    // The port A LEDs of the first VIA are the ones shown in the GUI
+    vias: Vec<ViaSlot>,
+    // Index in vias of the VIA currently calling the ViaSystem functions
+    current_via: usize,
+    irq_line: IrqLine,
     step_wait_time: usize,
     opcode_fetching: bool,
     cycle_count: usize,
@@ -115,8 +201,13 @@ pub struct PhysSystem {
     tx_log_msgs: Sender<LogMessage>,
     tx_gui_msgs: Sender<ToGuiMessage>,
     rx_sys_msgs: Receiver<ToSysMessage>,
-    tx_to_lcd: Option<Sender<SysToLcdMessage>>,
-    lcd_handle: Option<JoinHandle<()>>,
+    lcd: Option<LCD>,
+    acia: Option<AciaSlot>,
+    inputs: Inputs,
+    timeline: Timeline,
+    history: InstrHistory,
+    brk_hit: bool,
+    timeline_stop: bool,
 }
 
 impl Default for PhysSystem {
@@ -124,18 +215,32 @@ impl Default for PhysSystem {
         let (tx_log_msgs, _) = mpsc::channel();
         let (tx_gui_msgs, _) = mpsc::channel();
         let (_, rx_sys_msgs) = mpsc::channel();
+        let mut irq_line = IrqLine::new();
+        let wiring = Wiring::default_for(0x6000);
+        let vias = vec![ViaSlot::new(0x6000, &wiring, &mut irq_line)];
         PhysSystem {
             prgm_config: Config {
                 lcd_enabled: false,
+                lcd_operation_log: false,
                 allow_garbage: false,
+                crash_dump_dir: None,
+                dump_on_stp: false,
+                via_addrs: vec![0x6000],
+                wiring,
+                lcd_screenshot: None,
+                lcd_geometry: Geometry::default(),
+                lcd_char_rom: CharRom::a00(),
+                clock_freq: 1_000_000,
+                input_bounce_time: DEFAULT_BOUNCE_TIME,
+                timeline: Timeline::default(),
+                acia_addr: None,
+                acia_port: HostPort::Stdio,
+                acia_xmit_bug: false,
             },
             mem: [Data { data: 0xff, is_garbage: true }; 65_536],
-            via: via::W65C22S::new(),
-            via_pb: rand::random(),
-            pb_changed: false,
-            via_pa: rand::random(),
-            pa_changed: false,
-            irq: false,
+            vias,
+            current_via: 0,
+            irq_line,
             step_wait_time: DEFAULT_STEP_WAIT * 1000,
             opcode_fetching: false,
             cycle_count: 0,
@@ -147,14 +252,19 @@ impl Default for PhysSystem {
             tx_log_msgs,
             tx_gui_msgs,
             rx_sys_msgs,
-            tx_to_lcd: None,
-            lcd_handle: None,
+            lcd: None,
+            acia: None,
+            inputs: Inputs::new(1_000_000, DEFAULT_BOUNCE_TIME),
+            timeline: Timeline::default(),
+            history: InstrHistory::new(),
+            brk_hit: false,
+            timeline_stop: false,
         }
     }
 }
 
 /// A system with 16K of RAM, 32K of programmable (EEP)ROM,
-/// and a 6522 mapped to $6000.
+/// and 6522s mapped in the I/O space between them ($6000 by default).
 impl PhysSystem {
     pub fn new(
         prgm_config: Config,
@@ -163,13 +273,25 @@ impl PhysSystem {
         tx_gui_msgs: Sender<ToGuiMessage>,
         rx_sys_msgs: Receiver<ToSysMessage>
     ) -> PhysSystem {
-        let (tx_to_lcd, lcd_handle) = if prgm_config.lcd_enabled {
-            let (tx_to_lcd, rx_from_sys) = mpsc::channel();
-            let lcd = LCD::new(Sender::clone(&tx_log_msgs), Sender::clone(&tx_gui_msgs));
+        let lcd = if prgm_config.lcd_enabled {
+            let mut lcd = LCD::new(prgm_config.lcd_geometry, prgm_config.lcd_char_rom.clone(), prgm_config.clock_freq, prgm_config.lcd_screenshot.clone(), 
+                Sender::clone(&tx_log_msgs), Sender::clone(&tx_gui_msgs));
 
-            (Some(tx_to_lcd), Some(lcd.run(rx_from_sys)))
+            let wiring = &prgm_config.wiring;
+            let connected_data_pins = wiring.via_addrs()
+                .flat_map(|addr| wiring.nets_of(addr))
+                .flat_map(|net| net.periph_pins)
+                .fold(0, |mask, periph_pin| match periph_pin {
+                    PeriphPin::Lcd(LcdPin::Data(bit)) => mask | (1 << bit),
+                    _ => mask,
+                });
+            lcd.set_connected_data_pins(connected_data_pins);
+            if prgm_config.lcd_operation_log {
+                lcd.enable_operation_log();
+            }
+            Some(lcd)
         } else {
-            (None, None)
+            None
         };
         
         let mut mem: [Data<u8>; 65_536] = [Data::<u8>::new_garbage(); 65_536];
@@ -178,18 +300,45 @@ impl PhysSystem {
             mem[i].is_garbage = false;
         }
 
+        let mut irq_line = IrqLine::new();
+        let vias = prgm_config.via_addrs.iter()
+            .map(|&base_addr| ViaSlot::new(base_addr, &prgm_config.wiring, &mut irq_line))
+            .collect();
+        let inputs = Inputs::new(prgm_config.clock_freq, prgm_config.input_bounce_time);
+        let timeline = prgm_config.timeline.clone();
+        let acia = prgm_config.acia_addr.map(|base_addr| AciaSlot {
+            base_addr,
+            acia: W65C51::new(prgm_config.clock_freq, prgm_config.acia_xmit_bug),
+            irq_source: irq_line.add_source(&format!("ACIA ${:04x}", base_addr)),
+            bridge: SerialBridge::open(&prgm_config.acia_port),
+        });
+
         PhysSystem {
             prgm_config,
             mem,
+            vias,
+            irq_line,
             tx_log_msgs,
             tx_gui_msgs,
             rx_sys_msgs,
-            tx_to_lcd,
-            lcd_handle,
+            lcd,
+            acia,
+            inputs,
+            timeline,
             ..Default::default()
         }
     }
 
+    /// What the LCD shows and the state behind it, None if the LCD is disabled.
+    pub fn lcd_state(&self) -> Option<LcdState> {
+        self.lcd.as_ref().map(LCD::state)
+    }
+
+    /// Text shown on that row of the LCD, see `LcdState::lines`.
+    pub fn lcd_line(&self, row: usize) -> Option<String> {
+        self.lcd.as_ref().map(|lcd| lcd.line(row))
+    }
+
     pub fn run(mut self) -> thread::JoinHandle<()> {
         let mut cpu = W65C02S::new();
         let mut gui_running = true;
@@ -199,8 +348,8 @@ impl PhysSystem {
             'sys_thread_main: loop {
                 let sys_message = match self.currently_running {
                     true => {
-                        cpu.set_irq(self.irq);
-                        if self.step(&mut cpu) == State::Stopped {
+                        cpu.set_irq(self.irq_line.is_asserted());
+                        if self.checked_step(&mut cpu) == State::Stopped {
                             break 'sys_thread_main;
                         };
                         spin_sleep::sleep(time::Duration::from_micros(self.step_wait_time as u64));
@@ -219,17 +368,19 @@ impl PhysSystem {
                     (ToSysMessage::Run, false) => {
                         self.currently_running = true;
 
-                        self.send_lcd_msg(SysToLcdMessage::AllowOneUpdate);
+                        self.lcd_allow_one_update();
                     },
                     (ToSysMessage::Stop, true) => {
                         self.currently_running = false;
 
                         self.update_gui();
-                        self.send_lcd_msg(SysToLcdMessage::AllowAllUpdates);
+                        if let Some(lcd) = &mut self.lcd {
+                            lcd.allow_all_updates();
+                        }
                     },
                     (ToSysMessage::Step, false) => {
-                        cpu.set_irq(self.irq);
-                        if self.step(&mut cpu) == State::Stopped {
+                        cpu.set_irq(self.irq_line.is_asserted());
+                        if self.checked_step(&mut cpu) == State::Stopped {
                             break 'sys_thread_main;
                         }
                     }, 
@@ -243,16 +394,22 @@ impl PhysSystem {
                             else if self.step_wait_time <= 10_000 { 100 }
                             else { 0 };
 
-                        if self.screen_update_period == 0 {
-                            self.send_lcd_msg(SysToLcdMessage::AllowAllUpdates);
-                        } else {
-                            self.send_lcd_msg(SysToLcdMessage::AllowOneUpdate);
+                        if let Some(lcd) = &mut self.lcd {
+                            if self.screen_update_period == 0 {
+                                lcd.allow_all_updates();
+                            } else {
+                                lcd.allow_one_update();
+                            }
                         }
                     },
                     (ToSysMessage::ShowLog(print_log), _) => self.tx_log_msgs.send(
                         LogMessage::ChangePrintLog(print_log)
                     ).expect("Logger thread has hung up"),
                     (ToSysMessage::Breakpoint(pa_as_breakpoint), _) => self.pa_as_breakpoint = pa_as_breakpoint,
+                    (ToSysMessage::Input(command), _) => {
+                        log!(self.tx_log_msgs, "\nInput at cycle {}: {}", self.cycle_count, command);
+                        self.inputs.apply(command);
+                    },
                     (ToSysMessage::Exit, _) => {
                         gui_running = false;
                         break 'sys_thread_main;
@@ -268,15 +425,109 @@ impl PhysSystem {
                 self.send_gui_msg(ToGuiMessage::Stopped);
             }
 
-            if self.prgm_config.lcd_enabled {
-                self.send_lcd_msg(SysToLcdMessage::Exit);
-                print!("Waiting for LCD thread to end... ");
-                self.lcd_handle.unwrap().join().unwrap();
-                println!("LCD thread ended");
+            if let Some(lcd) = &self.lcd {
+                lcd.stop();
             }
         }).unwrap()
     }
 
+    /// Step the CPU, and write a crash dump if the step ends in a panic (e.g. garbage read),
+    /// a BRK, or an STP when those are considered crashes.
+    /// A BRK stops the system when crash dumps are enabled.
+    fn checked_step(&mut self, cpu: &mut W65C02S) -> State {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.step(cpu)));
+
+        match result {
+            Err(payload) => {
+                let reason = if let Some(msg) = payload.downcast_ref::<&str>() {
+                    msg.to_string()
+                } else if let Some(msg) = payload.downcast_ref::<String>() {
+                    msg.clone()
+                } else {
+                    String::from("Unknown panic")
+                };
+                self.write_crash_dump(cpu, &format!("Panic: {}", reason));
+                panic::resume_unwind(payload);
+            },
+            Ok(_) if self.brk_hit => {
+                self.brk_hit = false;
+                self.write_crash_dump(cpu, "BRK instruction");
+                State::Stopped
+            },
+            Ok(_) if self.timeline_stop => State::Stopped,
+            Ok(State::Stopped) => {
+                if self.prgm_config.dump_on_stp {
+                    self.write_crash_dump(cpu, "STP instruction");
+                }
+                State::Stopped
+            },
+            Ok(state) => state,
+        }
+    }
+
+    fn write_crash_dump(&self, cpu: &W65C02S, reason: &str) {
+        let dump_dir = match &self.prgm_config.crash_dump_dir {
+            Some(dump_dir) => dump_dir,
+            None => return,
+        };
+
+        let time_str = Local::now().format("%Y-%m-%d_%Hh%Mm%Ss");
+        // The cycle count tells apart the dumps written within the same second
+        let dump_path = dump_dir.join(format!("crash_{}_cycle{}.txt", time_str, self.cycle_count));
+        let mut dump = String::new();
+
+        dump.push_str(&format!("Crash reason: {}\n", reason));
+        dump.push_str(&format!("Cycle count: {}\nStep count: {}\n", self.cycle_count, self.step_count));
+        dump.push_str(&format!("CPU: PC={:04x} A={:02x} X={:02x} Y={:02x} S={:02x} P={:02x} {:?}\n",
+            cpu.get_pc(), cpu.get_a(), cpu.get_x(), cpu.get_y(), cpu.get_s(), cpu.get_p(), cpu.get_state()));
+        dump.push_str(&format!("IRQ: {} {:?}\n", 
+            self.irq_line.is_asserted(), self.irq_line.asserting_sources()));
+
+        dump.push_str("\n=== Instruction history (oldest first) ===\n");
+        dump.push_str("    Step      Cycle  Addr  Op Instruction\n");
+        for record in self.history.iter() {
+            dump.push_str(&format!("{}\n", record));
+        }
+
+        for slot in &self.vias {
+            dump.push_str(&format!("\n=== VIA ${:04x} ===\n", slot.base_addr));
+            dump.push_str(&format!("PORT B: {:#010b}\nPORT A: {:#010b}\n", slot.pb, slot.pa));
+            dump.push_str(&format!("{:#x?}\n", slot.via));
+        }
+
+        if let Some(slot) = &self.acia {
+            dump.push_str(&format!("\n=== ACIA ${:04x} ===\n", slot.base_addr));
+            dump.push_str(&format!("{:#x?}\n", slot.acia));
+        }
+
+        dump.push_str("\n=== LCD ===\n");
+        if let Some(lcd) = &self.lcd {
+            dump.push_str(&lcd.dump_state());
+        } else {
+            dump.push_str("LCD disabled");
+        }
+        dump.push('\n');
+
+        // Garbage bytes are shown as "??"
+        dump.push_str("\n=== RAM ($0000-$3fff) ===\n");
+        for line_addr in (0x0000..0x4000).step_by(16) {
+            dump.push_str(&format!("{:04x}:", line_addr));
+            for data in &self.mem[line_addr..line_addr + 16] {
+                if data.is_garbage {
+                    dump.push_str(" ??");
+                } else {
+                    dump.push_str(&format!(" {:02x}", data.data));
+                }
+            }
+            dump.push('\n');
+        }
+
+        match File::create(&dump_path).and_then(|mut file| file.write_all(dump.as_bytes())) {
+            Ok(()) => println!("Crash dump written to {}", dump_path.display()),
+            Err(err) => println!("Failed to write crash dump to {}: {}", dump_path.display(), err),
+        }
+    }
+
     fn step(&mut self, cpu: &mut W65C02S) -> State {
         if self.cycle_count > self.sent_cycle_count + self.screen_update_period || !self.currently_running {
             self.sent_cycle_count = self.cycle_count;
@@ -290,43 +541,268 @@ impl PhysSystem {
         cpu.step(self)
     }
 
-    fn update_gui(&self) {
+    fn update_gui(&mut self) {
         self.send_gui_msg(ToGuiMessage::CycleCount(self.cycle_count));
 
-        self.send_gui_msg(ToGuiMessage::PortB(self.via_pb));
-        self.send_gui_msg(ToGuiMessage::PortA(self.via_pa));
+        self.send_gui_msg(ToGuiMessage::PortB(self.vias[0].pb));
+        self.send_gui_msg(ToGuiMessage::PortA(self.vias[0].pa));
         
-        self.send_lcd_msg(SysToLcdMessage::AllowOneUpdate);
+        self.lcd_allow_one_update();
     }
 
     fn send_gui_msg(&self, msg: ToGuiMessage) {
         self.tx_gui_msgs.send(msg).expect("GUI thread has hung up");
     }
 
-    fn send_lcd_msg(&self, msg: SysToLcdMessage) {
-        if let Some(tx) = &self.tx_to_lcd {
-            tx.send(msg).expect("LCD thread has hung up");
+    fn lcd_allow_one_update(&mut self) {
+        if let Some(lcd) = &mut self.lcd {
+            lcd.allow_one_update();
         }
     }
-}
 
-impl System for PhysSystem {
-    fn read(&mut self, _cpu: &mut W65C02S, addr: u16) -> u8 {
-        let mut via = self.via;
+    // Index of the VIA mapped at addr, if any
+    fn via_at(&self, addr: u16) -> Option<usize> {
+        self.vias.iter().position(|slot| addr & 0xfff0 == slot.base_addr)
+    }
+
+    fn is_acia_addr(&self, addr: u16) -> bool {
+        matches!(&self.acia, Some(slot) if addr & 0xfffc == slot.base_addr)
+    }
+
+    // The peripherals driving a net, with the level each one drives
+    fn periph_drivers(&self, net: &Net) -> Vec<(Driver, bool)> {
+        net.periph_pins.iter().filter_map(|periph_pin| match periph_pin {
+            PeriphPin::Lcd(LcdPin::Data(bit)) => self.lcd.as_ref()
+                .and_then(LCD::data_output)
+                .filter(|output| output.driven & (1 << bit) != 0)
+                .map(|output| (Driver::Lcd, output.data & (1 << bit) != 0)),
+            PeriphPin::Lcd(_) => None,
+            // A closed contact shorts the net to the ground
+            PeriphPin::Input(input_pin) if self.inputs.is_closed(*input_pin) => Some((Driver::Input, false)),
+            PeriphPin::Input(_) => None,
+        }).collect()
+    }
+
+    // Level the peripherals drive on a net, if any
+    fn periph_level(&self, net: &Net) -> Option<bool> {
+        self.periph_drivers(net).first().map(|&(_, level)| level)
+    }
+
+    // What drives a port pin of the VIA currently calling the ViaSystem functions (or being checked)
+    fn drivers(&self, via: &via::W65C22S, pin: ViaPin) -> Vec<Driver> {
+        let mut drivers = Vec::new();
+        let via_drives = match pin {
+            ViaPin::PA(bit) => via.get_ddra() & (1 << bit) != 0,
+            ViaPin::PB(bit) => via.get_pb_outputs() & (1 << bit) != 0,
+            // Only the ports are checked, the control lines are left to the VIA
+            ViaPin::CA1 | ViaPin::CA2 | ViaPin::CB1 | ViaPin::CB2 => false,
+        };
+        if via_drives {
+            drivers.push(Driver::Via);
+        }
+        if self.vias[self.current_via].forced_pins & (1 << pin.index()) != 0 {
+            drivers.push(Driver::Timeline);
+        }
+        drivers.extend(self.periph_drivers(&self.vias[self.current_via].nets[pin.index()])
+            .into_iter()
+            .map(|(driver, _)| driver));
+        drivers
+    }
+
+    // Level of a VIA pin set as an input, for the VIA currently calling the ViaSystem functions
+    fn read_pin(&self, pin: ViaPin) -> bool {
+        let slot = &self.vias[self.current_via];
+        // The timeline overrides whatever is connected to the pin
+        if slot.forced_pins & (1 << pin.index()) != 0 {
+            return slot.forced_levels & (1 << pin.index()) != 0;
+        }
+        let net = &slot.nets[pin.index()];
+
+        // Without a peripheral driving the net, the level only depends on the resistors
+        self.periph_level(net).unwrap_or_else(|| match net.pull {
+            Pull::Up => true,
+            Pull::Down => false,
+            Pull::Floating => rand::random(),
+        })
+    }
+
+    // Levels of the port pins, flagging the inputs that nothing holds at a defined level
+    // the first time they are read, and again only once they have been driven in between
+    fn read_port(&mut self, via: &via::W65C22S, pin: fn(u8) -> ViaPin, inputs: u8) -> u8 {
+        let port_pins = (0..8).fold(0, |mask, bit| mask | (1 << pin(bit).index()));
+        let floating_pins = (0..8)
+            .filter(|bit| inputs & (1 << bit) != 0)
+            .map(pin)
+            .filter(|&pin| self.vias[self.current_via].nets[pin.index()].pull == Pull::Floating
+                && self.drivers(via, pin).is_empty())
+            .fold(0, |mask, pin| mask | (1 << pin.index()));
+
+        let slot = &mut self.vias[self.current_via];
+        let new_floating_pins: Vec<String> = (0..8)
+            .map(pin)
+            .filter(|pin| (floating_pins & !slot.floating_warned) & (1 << pin.index()) != 0)
+            .map(|pin| pin.to_string())
+            .collect();
+        slot.floating_warned = (slot.floating_warned & !port_pins) | floating_pins;
+        if !new_floating_pins.is_empty() {
+            log!(self.tx_log_msgs, "\nWARNING: reading floating {} of VIA ${:04x}, the levels are random", 
+                new_floating_pins.join(" "), slot.base_addr);
+        }
+
+        (0..8).fold(0, |data, bit| data | ((self.read_pin(pin(bit)) as u8) << bit))
+    }
 
+    // Flag the port pins driven by several outputs at once, which would damage the real chips
+    fn check_contention(&mut self) {
+        for i in 0..self.vias.len() {
+            // Nothing but the VIAs drives the pins most of the time
+            if self.vias[i].contended_pins == 0 && self.vias[i].forced_pins == 0
+                && self.lcd.as_ref().and_then(LCD::data_output).is_none() && !self.inputs.any_closed() {
+                continue;
+            }
+
+            self.current_via = i;
+            let via = self.vias[i].via;
+            let mut contended_pins = 0;
+            let mut new_contentions = Vec::new();
+            for pin in (0..8).map(ViaPin::PA).chain((0..8).map(ViaPin::PB)) {
+                let drivers = self.drivers(&via, pin);
+                if drivers.len() > 1 {
+                    contended_pins |= 1 << pin.index();
+                    if self.vias[i].contended_pins & (1 << pin.index()) == 0 {
+                        new_contentions.push(format!("{} ({:?})", pin, drivers));
+                    }
+                }
+            }
+
+            if !new_contentions.is_empty() {
+                log!(self.tx_log_msgs, "\nWARNING: bus contention on VIA ${:04x}, {} driven by several outputs at once", 
+                    self.vias[i].base_addr, new_contentions.join(", "));
+            }
+            self.vias[i].contended_pins = contended_pins;
+        }
+    }
+
+    // Forward a level driven by the VIA currently calling the ViaSystem functions to the peripherals
+    fn drive_pin(&mut self, pin: ViaPin, level: bool) {
+        for periph_pin in &self.vias[self.current_via].nets[pin.index()].periph_pins {
+            match (periph_pin, &mut self.lcd) {
+                (PeriphPin::Lcd(LcdPin::Register), Some(lcd)) => lcd.register_pin_change(level),
+                (PeriphPin::Lcd(LcdPin::ReadWrite), Some(lcd)) => lcd.read_write_pin_change(level),
+                (PeriphPin::Lcd(LcdPin::Enable), Some(lcd)) => lcd.enable_pin_change(level),
+                (PeriphPin::Lcd(LcdPin::Data(bit)), Some(lcd)) => lcd.data_pin_change(*bit, level),
+                (PeriphPin::Lcd(_), None) => {},
+                // The contacts don't care about the level on their net
+                (PeriphPin::Input(_), _) => {},
+            }
+        }
+    }
+
+    // Apply the timeline steps happening now, pc being the address of the opcode being fetched if any
+    fn run_timeline(&mut self, pc: Option<u16>) {
+        for (action, text) in self.timeline.due(self.cycle_count, pc) {
+            log!(self.tx_log_msgs, "\nTimeline at cycle {}: {}", self.cycle_count, text);
+
+            match action {
+                Action::Input(command) => self.inputs.apply(command),
+                Action::Pull(via_addr, pin, level) => {
+                    let slot = match via_addr {
+                        Some(via_addr) => self.vias.iter_mut()
+                            .find(|slot| slot.base_addr == via_addr)
+                            .expect("The timeline drives a VIA which isn't mapped"),
+                        None => &mut self.vias[0],
+                    };
+                    let bit = 1 << pin.index();
+                    match level {
+                        Some(level) => {
+                            slot.forced_pins |= bit;
+                            slot.forced_levels = if level { slot.forced_levels | bit } else { slot.forced_levels & !bit };
+                        },
+                        None => slot.forced_pins &= !bit,
+                    }
+                },
+                Action::Stop => self.timeline_stop = true,
+            }
+        }
+    }
+
+    // One cycle of the system clock: the peripherals are advanced in step with the CPU
+    fn clock_pulse(&mut self) {
         self.cycle_count += 1;
-        via.clock_pulse(self);
+        self.run_timeline(None);
+        if let Some(lcd) = &mut self.lcd {
+            lcd.clock(self.cycle_count);
+        }
+        self.inputs.clock(self.cycle_count);
+        self.clock_vias();
+        self.clock_acia();
+        // The drivers only change on the accesses of the previous cycle, or on T1 toggling PB7
+        self.check_contention();
+    }
+
+    // Every VIA shares the PHI2 clock of the CPU
+    fn clock_vias(&mut self) {
+        for i in 0..self.vias.len() {
+            self.current_via = i;
+            let mut via = self.vias[i].via;
+            via.clock_pulse(self);
+            self.vias[i].via = via;
+        }
+    }
+
+    fn clock_acia(&mut self) {
+        if let Some(slot) = &self.acia {
+            let mut acia = slot.acia;
+            acia.clock_pulse(self);
+            self.acia.as_mut().unwrap().acia = acia;
+        }
+    }
+
+    fn read_acia(&mut self, register_select: u8) -> u8 {
+        let mut acia = self.acia.as_ref().unwrap().acia;
+        let value = acia.read(self, register_select);
+        self.acia.as_mut().unwrap().acia = acia;
+        value
+    }
 
-        let value = match addr {
+    fn write_acia(&mut self, register_select: u8, value: u8) {
+        let mut acia = self.acia.as_ref().unwrap().acia;
+        acia.write(self, register_select, value);
+        self.acia.as_mut().unwrap().acia = acia;
+    }
+
+    fn read_via(&mut self, i: usize, register_select: u8) -> u8 {
+        self.current_via = i;
+        let mut via = self.vias[i].via;
+        let value = via.read(self, register_select);
+        self.vias[i].via = via;
+        value
+    }
+
+    fn write_via(&mut self, i: usize, register_select: u8, value: u8) {
+        self.current_via = i;
+        let mut via = self.vias[i].via;
+        via.write(self, register_select, value);
+        self.vias[i].via = via;
+    }
+}
+
+impl System for PhysSystem {
+    fn read(&mut self, cpu: &mut W65C02S, addr: u16) -> u8 {
+        self.clock_pulse();
+
+        let value = match (addr, self.via_at(addr)) {
+            // read from a VIA
+            (_, Some(i)) => self.read_via(i, (addr as u8) & 0b0000_1111),
+            // read from the ACIA
+            _ if self.is_acia_addr(addr) => self.read_acia((addr as u8) & 0b0000_0011),
             // read from STACK (don't trigger panic on garbage read)
-            0x0100..=0x01ff => self.mem[addr as usize].data,
+            (0x0100..=0x01ff, None) => self.mem[addr as usize].data,
             // read from RAM
-            0x0000..=0x00ff | 0x0200..=0x3fff => self.mem[addr as usize].read(self.prgm_config.allow_garbage, 
+            (0x0000..=0x00ff, None) | (0x0200..=0x3fff, None) => self.mem[addr as usize].read(self.prgm_config.allow_garbage, 
                 &self.tx_log_msgs, &format!("\nCPU reading garbage RAM data at addr {:04x}!", addr)),
-            // read from VIA
-            0x6000..=0x600f => via.read(self, (addr as u8) & 0b0000_1111),
             // read from ROM
-            0x8000..=0xffff => self.mem[addr as usize].read(self.prgm_config.allow_garbage, 
+            (0x8000..=0xffff, None) => self.mem[addr as usize].read(self.prgm_config.allow_garbage, 
                 &self.tx_log_msgs, &format!("\nCPU reading garbage ROM data at addr {:04x}!", addr)),
             _ => {
                 log!(self.tx_log_msgs, "\nCPU reading garbage ROM data at addr {:04x}!", addr);
@@ -343,102 +819,414 @@ impl System for PhysSystem {
         if self.opcode_fetching {
             self.opcode_fetching = false;
             log!(self.tx_log_msgs, " {}", OPCODES[value as usize]);
+
+            self.history.push(InstrRecord {
+                step: self.step_count - 1,
+                cycle: self.cycle_count,
+                pc: addr,
+                opcode: value,
+                a: cpu.get_a(),
+                x: cpu.get_x(),
+                y: cpu.get_y(),
+                s: cpu.get_s(),
+                p: cpu.get_p(),
+            });
+
+            if value == 0x00 && self.prgm_config.crash_dump_dir.is_some() {
+                self.brk_hit = true;
+            }
+
+            self.run_timeline(Some(addr));
         }
 
-        self.via = via;
         value
     }
 
-    fn write(&mut self, _cpu: &mut W65C02S, addr: u16, value: u8) {
-        let mut via = self.via;
+    // The next opcode is read but not executed when an interrupt is taken, 
+    // and after a WAI or an STP until the CPU leaves that state
+    fn read_opcode_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.opcode_fetching = false;
+        self.read(cpu, addr);
+    }
 
-        self.cycle_count += 1;
-        via.clock_pulse(self);
+    fn read_operand_spurious(&mut self, cpu: &mut W65C02S, addr: u16) {
+        self.opcode_fetching = false;
+        self.read(cpu, addr);
+    }
+
+    fn write(&mut self, _cpu: &mut W65C02S, addr: u16, value: u8) {
+        self.clock_pulse();
 
         log!(self.tx_log_msgs, "\n    WRITE {:02x} at {:04x}", value, addr);
 
-        match addr {
-            // write to RAM (note that writes to 4000-7fff are useless but still happen on the physical system)
-            0x0000..=0x5fff | 0x6010..=0x7fff => self.mem[addr as usize].write_valid(value),
-            // write to VIA
-            0x6000..=0x600f => {
+        match (addr, self.via_at(addr)) {
+            // write to a VIA
+            (_, Some(i)) => {
+                self.mem[addr as usize].write_valid(value);
+                self.write_via(i, (addr as u8) & 0b0000_1111, value);
+            },
+            // write to the ACIA
+            _ if self.is_acia_addr(addr) => {
                 self.mem[addr as usize].write_valid(value);
-                via.write(self, (addr as u8) & 0b0000_1111, value);
+                self.write_acia((addr as u8) & 0b0000_0011, value);
             },
+            // write to RAM (note that writes to 4000-7fff are useless but still happen on the physical system)
+            (0x0000..=0x7fff, None) => self.mem[addr as usize].write_valid(value),
             // the write is useless
             _ => {},
         };
 
-        if self.pb_changed {
-            self.pb_changed = false;
-            log!(self.tx_log_msgs, " -> PORT B: {:#010b} {:#04x} {}", 
-                self.via_pb, self.via_pb, self.via_pb);
-        }
+        for i in 0..self.vias.len() {
+            let slot = &mut self.vias[i];
 
-        if self.pa_changed {
-            self.pa_changed = false;
-            log!(self.tx_log_msgs, " -> PORT A: {:#010b} {:#04x} {}", 
-                self.via_pa, self.via_pa, self.via_pa);
+            if slot.pb_changed {
+                slot.pb_changed = false;
+                log!(self.tx_log_msgs, " -> PORT B (${:04x}): {:#010b} {:#04x} {}", 
+                    slot.base_addr, slot.pb, slot.pb, slot.pb);
+            }
 
-            // Breakpoint mecanism
-            if self.pa_as_breakpoint && self.currently_running {
-                self.currently_running = false;
-                self.update_gui();
+            if slot.pa_changed {
+                slot.pa_changed = false;
+                log!(self.tx_log_msgs, " -> PORT A (${:04x}): {:#010b} {:#04x} {}", 
+                    slot.base_addr, slot.pa, slot.pa, slot.pa);
+
+                // Breakpoint mecanism, on the port A LEDs of the first VIA
+                if i == 0 && self.pa_as_breakpoint && self.currently_running {
+                    self.currently_running = false;
+                    self.update_gui();
+                }
             }
         }
-
-        self.via = via;
     }
 }
 
 impl via::ViaSystem for PhysSystem {
-    fn read_port_b(&mut self, _via: &mut via::W65C22S) -> u8 {
-        // lcd.read()
-        rand::random()
+    fn read_port_b(&mut self, via: &mut via::W65C22S) -> u8 {
+        self.read_port(via, ViaPin::PB, !via.get_ddrb())
     }
     
-    fn read_port_a(&mut self, _via: &mut via::W65C22S) -> u8 {
-        // Nothing connected to Port A yet, thus send random value on floating pins
-        rand::random()
+    fn read_port_a(&mut self, via: &mut via::W65C22S) -> u8 {
+        // The output pins read the level the VIA drives on them
+        let ddra = via.get_ddra();
+        (self.vias[self.current_via].pa & ddra) | (self.read_port(via, ViaPin::PA, !ddra) & !ddra)
     }
 
     fn write_port_b(&mut self, _via: &mut via::W65C22S, bit: u8, level: bool) {
+        let slot = &mut self.vias[self.current_via];
+
         // Update PB bus
-        self.via_pb = match level {
-            true => self.via_pb | (1 << bit),
-            false => self.via_pb & !(1 << bit),
+        slot.pb = match level {
+            true => slot.pb | (1 << bit),
+            false => slot.pb & !(1 << bit),
         };
 
-        self.pb_changed = true;
-        
-        // If the LCD screen is enabled, send it data
-        if let Some(tx) = &self.tx_to_lcd {
-            match bit {
-                0..=3 => tx.send(SysToLcdMessage::DataPinChange((bit + 4, level))),
-                5 => tx.send(SysToLcdMessage::EnablePinChange(level)),
-                6 => tx.send(SysToLcdMessage::ReadWritePinChange(level)),
-                7 => tx.send(SysToLcdMessage::RegisterPinChange(level)),
-                // PB4 isn't connected to the LCD
-                _ => { Ok(()) },
-            }.expect("LCD thread has hung up");
-        }
+        slot.pb_changed = true;
+
+        self.drive_pin(ViaPin::PB(bit), level);
     }
 
     fn write_port_a(&mut self, _via: &mut via::W65C22S, bit: u8, level: bool) {
+        let slot = &mut self.vias[self.current_via];
+
         // Update PA bus
-        self.via_pa = match level {
-            true => self.via_pa | (1 << bit),
-            false => self.via_pa & !(1 << bit),
+        slot.pa = match level {
+            true => slot.pa | (1 << bit),
+            false => slot.pa & !(1 << bit),
         };
 
-        self.pa_changed = true;
+        slot.pa_changed = true;
+
+        self.drive_pin(ViaPin::PA(bit), level);
+    }
+
+    fn write_ca2(&mut self, _via: &mut via::W65C22S, level: bool) {
+        self.drive_pin(ViaPin::CA2, level);
+    }
+
+    fn write_cb1(&mut self, _via: &mut via::W65C22S, level: bool) {
+        self.drive_pin(ViaPin::CB1, level);
+    }
+
+    fn write_cb2(&mut self, _via: &mut via::W65C22S, level: bool) {
+        self.drive_pin(ViaPin::CB2, level);
+    }
+
+    fn read_ca1(&mut self, _via: &mut via::W65C22S) -> bool {
+        self.read_pin(ViaPin::CA1)
     }
 
-    fn write_cb2(&mut self, _via: &mut via::W65C22S, _level: bool) {
-        // Nothing connected to that yet
+    fn read_ca2(&mut self, _via: &mut via::W65C22S) -> bool {
+        self.read_pin(ViaPin::CA2)
+    }
+
+    fn read_cb1(&mut self, _via: &mut via::W65C22S) -> bool {
+        self.read_pin(ViaPin::CB1)
+    }
+
+    fn read_cb2(&mut self, _via: &mut via::W65C22S) -> bool {
+        self.read_pin(ViaPin::CB2)
     }
 
     fn update_irq(&mut self, _via: &mut via::W65C22S, irq: bool) {
-        self.irq = irq;
+        self.irq_line.set(self.vias[self.current_via].irq_source, irq);
+    }
+}
+
+impl acia::AciaSystem for PhysSystem {
+    fn receive_byte(&mut self, _acia: &mut W65C51) -> Option<u8> {
+        let byte = self.acia.as_ref().and_then(|slot| slot.bridge.try_recv());
+        if let Some(byte) = byte {
+            log!(self.tx_log_msgs, "\nACIA receiving {:#04x}", byte);
+        }
+        byte
+    }
+
+    fn transmit_byte(&mut self, _acia: &mut W65C51, byte: u8) {
+        log!(self.tx_log_msgs, "\nACIA sent {:#04x}", byte);
+        if let Some(slot) = &mut self.acia {
+            slot.bridge.send(byte);
+        }
+    }
+
+    fn update_irq(&mut self, _acia: &mut W65C51, irq: bool) {
+        if let Some(slot) = &self.acia {
+            self.irq_line.set(slot.irq_source, irq);
+        }
+    }
+
+    fn warn(&mut self, _acia: &mut W65C51, msg: &str) {
+        log!(self.tx_log_msgs, "\nACIA WARNING: {}", msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_sys() -> (PhysSystem, Receiver<LogMessage>) {
+        let (tx_log_msgs, rx_log_msgs) = mpsc::channel();
+        (PhysSystem {
+            tx_log_msgs,
+            ..Default::default() 
+        }, rx_log_msgs)
+    }
+
+    // Send 4 bits to the LCD through port B, as wired by default, then wait for it to be done
+    fn send_lcd_nibble(sys: &mut PhysSystem, cpu: &mut W65C02S, rs: bool, nibble: u8) {
+        let pb = (rs as u8) << 7 | nibble;
+        sys.write(cpu, 0x6000, pb);
+        sys.write(cpu, 0x6000, pb | 0b0010_0000);
+        sys.write(cpu, 0x6000, pb);
+        for _ in 0..100 {
+            sys.read(cpu, 0x0100);
+        }
+    }
+
+    #[test]
+    fn lcd_state() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        assert_eq!(None, sys.lcd_state());
+
+        let (tx_gui_msgs, _rx_gui_msgs) = mpsc::channel();
+        sys.lcd = Some(LCD::new(Geometry::default(), CharRom::a00(), 1_000_000, None, 
+            sys.tx_log_msgs.clone(), tx_gui_msgs));
+        let mut cpu = W65C02S::new();
+
+        // Port B as outputs, then 4-bit initialization, 2 lines, display on
+        sys.write(&mut cpu, 0x6002, 0xff);
+        for &nibble in &[0x3, 0x3, 0x3, 0x2, 0x2, 0x8, 0x0, 0xc] {
+            send_lcd_nibble(&mut sys, &mut cpu, false, nibble);
+        }
+        for &byte in b"Hi" {
+            send_lcd_nibble(&mut sys, &mut cpu, true, byte >> 4);
+            send_lcd_nibble(&mut sys, &mut cpu, true, byte & 0x0f);
+        }
+
+        assert_eq!(Some(String::from("Hi")), sys.lcd_line(0));
+        let state = sys.lcd_state().unwrap();
+        assert_eq!(vec!["Hi", ""], state.lines);
+        assert_eq!(0x02, state.cursor_addr);
+        assert!(state.display_on);
+    }
+
+    #[test]
+    fn lcd_8bit_wiring_in_4bit_mode() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        sys.vias[0].nets = Wiring::parse("[6000]\nPA0 LCD.D0\nPA1 LCD.D1\nPA2 LCD.D2\nPA3 LCD.D3\n\
+            PB0 LCD.D4\nPB1 LCD.D5\nPB2 LCD.D6\nPB3 LCD.D7\nPB5 LCD.E\nPB6 LCD.RW\nPB7 LCD.RS").unwrap().nets_of(0x6000);
+        let (tx_gui_msgs, _rx_gui_msgs) = mpsc::channel();
+        sys.lcd = Some(LCD::new(Geometry::default(), CharRom::a00(), 1_000_000, None, 
+            sys.tx_log_msgs.clone(), tx_gui_msgs));
+        let mut cpu = W65C02S::new();
+
+        // Both ports as outputs, then 4-bit initialization, 2 lines, display on
+        sys.write(&mut cpu, 0x6002, 0xff);
+        sys.write(&mut cpu, 0x6003, 0x0f);
+        for &nibble in &[0x3, 0x3, 0x3, 0x2, 0x2, 0x8, 0x0, 0xc] {
+            send_lcd_nibble(&mut sys, &mut cpu, false, nibble);
+        }
+        // DB0-DB3 change between the two halves of a transfer, which the LCD doesn't read
+        send_lcd_nibble(&mut sys, &mut cpu, true, b'H' >> 4);
+        sys.write(&mut cpu, 0x6001, 0x0f);
+        sys.write(&mut cpu, 0x6001, 0x05);
+        send_lcd_nibble(&mut sys, &mut cpu, true, b'H' & 0x0f);
+
+        assert_eq!(Some(String::from("H")), sys.lcd_line(0));
+    }
+
+    #[test]
+    fn input_devices() {
+        let (mut sys, rx_log_msgs) = create_test_sys();
+        let wiring = Wiring::parse("[6000]\nPA0 SW.0 pullup\nPA1 BTN.1 pullup\nCA1 BTN.2 pullup\n\
+            PA4 pullup\nPA5 pullup\nPA6 pullup\nPA7 pullup").unwrap();
+        sys.vias[0].nets = wiring.nets_of(0x6000);
+        sys.inputs = Inputs::new(1_000_000, 0);
+        let mut cpu = W65C02S::new();
+
+        // Port A as inputs, held high by the pull-ups until a contact closes
+        sys.write(&mut cpu, 0x6003, 0x00);
+        assert_eq!(0b0000_0011, sys.read(&mut cpu, 0x6001) & 0b0000_0011);
+        sys.inputs.apply(InputCommand::Switch(0, true));
+        sys.inputs.apply(InputCommand::Press(1));
+        assert_eq!(0b0000_0000, sys.read(&mut cpu, 0x6001) & 0b0000_0011);
+        sys.inputs.apply(InputCommand::Release(1));
+        assert_eq!(0b0000_0010, sys.read(&mut cpu, 0x6001) & 0b0000_0011);
+
+        // A button on CA1 triggers the interrupt on its falling edge, once enabled in IER
+        sys.write(&mut cpu, 0x600e, 0b1000_0010);
+        sys.read(&mut cpu, 0x0100);
+        assert!(!sys.irq_line.is_asserted());
+        sys.inputs.apply(InputCommand::Press(2));
+        sys.read(&mut cpu, 0x0100);
+        sys.read(&mut cpu, 0x0100);
+        assert!(sys.irq_line.is_asserted());
+        rx_log_msgs.try_iter().for_each(drop);
+
+        // PA0 as an output driving high, shorted to the ground by the switch
+        sys.write(&mut cpu, 0x6001, 0b0000_0001);
+        sys.write(&mut cpu, 0x6003, 0b0000_0001);
+        sys.read(&mut cpu, 0x0100);
+        let contentions: Vec<String> = rx_log_msgs.try_iter().filter_map(|msg| match msg {
+            LogMessage::Log(msg) if msg.contains("WARNING") => Some(msg),
+            _ => None,
+        }).collect();
+        assert_eq!(1, contentions.len());
+        assert!(contentions[0].contains("bus contention on VIA $6000, PA0 ([Via, Input]) driven"));
+    }
+
+    #[test]
+    fn timeline_pins() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        sys.vias[0].nets = Wiring::parse("[6000]\nPA3 pullup").unwrap().nets_of(0x6000);
+        sys.timeline = Timeline::parse("at 10 pull PA3 low\nafter 5 free PA3\nwhen pc 0100 stop").unwrap();
+        let mut cpu = W65C02S::new();
+
+        sys.write(&mut cpu, 0x6003, 0x00);
+        assert_eq!(0b0000_1000, sys.read(&mut cpu, 0x6001) & 0b0000_1000);
+        while sys.cycle_count < 10 {
+            sys.read(&mut cpu, 0x0100);
+        }
+        assert_eq!(0b0000_0000, sys.read(&mut cpu, 0x6001) & 0b0000_1000, "The timeline pulls PA3 low");
+        while sys.cycle_count < 15 {
+            sys.read(&mut cpu, 0x0100);
+        }
+        assert_eq!(0b0000_1000, sys.read(&mut cpu, 0x6001) & 0b0000_1000, "The pull-up holds PA3 again");
+
+        // Only opcode fetches trigger the PC steps
+        sys.read(&mut cpu, 0x0100);
+        assert!(!sys.timeline_stop);
+        sys.step_count = 1;
+        sys.opcode_fetching = true;
+        sys.read(&mut cpu, 0x0100);
+        assert!(sys.timeline_stop);
+    }
+
+    #[test]
+    fn brk_only_on_executed_opcodes() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        let (tx_gui_msgs, _rx_gui_msgs) = mpsc::channel();
+        sys.tx_gui_msgs = tx_gui_msgs;
+        sys.prgm_config.crash_dump_dir = Some(std::env::temp_dir());
+        // Reset to $0200: WAI, then BRK, the IRQ handler at $0300 being a BRK too
+        for &(addr, value) in &[(0xfffc, 0x00), (0xfffd, 0x02), (0xfffe, 0x00), (0xffff, 0x03),
+            (0x0200, 0xcb), (0x0201, 0x00), (0x0300, 0x00), (0x0301, 0xea)] {
+            sys.mem[addr] = Data { data: value, is_garbage: false };
+        }
+        let mut cpu = W65C02S::new();
+        cpu.set_pc(0x0200);
+
+        // Reset, WAI, then waiting in front of the BRK
+        for _ in 0..4 {
+            sys.step(&mut cpu);
+        }
+        assert_eq!(State::AwaitingInterrupt, cpu.get_state());
+        assert!(!sys.brk_hit, "The BRK after the WAI isn't executed yet");
+
+        // The IRQ wakes the CPU up, and is taken in front of the BRK
+        cpu.set_p(cpu.get_p() & !0x04);
+        cpu.set_irq(true);
+        sys.step(&mut cpu);
+        sys.step(&mut cpu);
+        assert_eq!(0x0300, cpu.get_pc());
+        assert!(!sys.brk_hit, "The BRK preempted by the IRQ isn't executed");
+
+        sys.step(&mut cpu);
+        assert!(sys.brk_hit);
+    }
+
+    #[test]
+    fn ps2_keyboard() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        sys.vias[0].nets = Wiring::parse("[6000]\nCA1 KBD.CLK pullup\nPA0 KBD.DATA pullup").unwrap().nets_of(0x6000);
+        sys.inputs.apply(InputCommand::parse("key a").unwrap());
+        let mut cpu = W65C02S::new();
+
+        // Polling IFR1 like a keyboard driver, the CA1 flag being set on the falling edges of the clock
+        sys.write(&mut cpu, 0x6003, 0x00);
+        let mut bits = Vec::new();
+        while sys.cycle_count < 3 * 1_080 {
+            if sys.read(&mut cpu, 0x600d) & 0b0000_0010 != 0 {
+                // Reading port A clears the flag
+                bits.push(sys.read(&mut cpu, 0x6001) & 0b0000_0001);
+            }
+        }
+
+        let bytes: Vec<u8> = bits.chunks(11)
+            .map(|frame| frame[1..9].iter().rev().fold(0, |byte, &bit| (byte << 1) | bit))
+            .collect();
+        assert_eq!(33, bits.len());
+        assert_eq!(vec![0x1c, 0xf0, 0x1c], bytes);
+    }
+
+    #[test]
+    fn acia_mapped() {
+        let (mut sys, rx_log_msgs) = create_test_sys();
+        let (tx_bytes, rx_bytes) = mpsc::channel();
+        sys.acia = Some(AciaSlot {
+            base_addr: 0x5000,
+            acia: W65C51::new(1_000_000, false),
+            irq_source: sys.irq_line.add_source("ACIA $5000"),
+            bridge: SerialBridge::with_channels(rx_bytes, Box::new(std::io::sink())),
+        });
+        let mut cpu = W65C02S::new();
+
+        // 115200 bauds, 8N1, receive interrupt enabled, transmitter on
+        sys.write(&mut cpu, 0x5003, 0b0001_0000);
+        sys.write(&mut cpu, 0x5002, 0b0000_1001);
+        sys.write(&mut cpu, 0x5000, b'A');
+        tx_bytes.send(b'z').unwrap();
+        for _ in 0..100 {
+            sys.read(&mut cpu, 0x0100);
+        }
+
+        assert!(sys.irq_line.is_asserted());
+        assert_eq!(vec!["ACIA $5000"], sys.irq_line.asserting_sources());
+        assert_eq!(0b1001_1000, sys.read(&mut cpu, 0x5001));
+        assert_eq!(b'z', sys.read(&mut cpu, 0x5000));
+        assert!(!sys.irq_line.is_asserted());
+        assert!(rx_log_msgs.try_iter().any(|msg| match msg {
+            LogMessage::Log(msg) => msg == "\nACIA sent 0x41",
+            _ => false,
+        }));
     }
 }
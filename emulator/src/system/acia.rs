@@ -0,0 +1,379 @@
+pub trait AciaSystem {
+    /// Called by the ACIA when its receiver is ready for the next byte,
+    /// which starts arriving on RxD if there is one.
+    fn receive_byte(&mut self, acia: &mut W65C51) -> Option<u8>;
+
+    /// Called by the ACIA once the last stop bit of a byte went out on TxD.
+    fn transmit_byte(&mut self, acia: &mut W65C51, byte: u8);
+
+    /// Receive an update from the `IRQB` pin, as the *logical* value (see `ViaSystem::update_irq`).
+    fn update_irq(&mut self, acia: &mut W65C51, irq: bool);
+
+    /// Called by the ACIA when the program uses it in a way the real chip doesn't handle.
+    fn warn(&mut self, acia: &mut W65C51, msg: &str);
+}
+
+const DATA: u8 = 0x0;
+const STATUS: u8 = 0x1;
+const COMMAND: u8 = 0x2;
+const CONTROL: u8 = 0x3;
+
+const STATUS_OVERRUN: u8 = 0b0000_0100;
+const STATUS_RDRF: u8 = 0b0000_1000;
+const STATUS_TDRE: u8 = 0b0001_0000;
+const STATUS_IRQ: u8 = 0b1000_0000;
+
+// Baud rates selected by the low nibble of the control register.
+// 0 is the 16x external clock, taken as the usual 1.8432 MHz crystal divided by 16.
+const BAUD_RATES: [f64; 16] = [
+    115_200.0, 50.0, 75.0, 109.92, 134.58, 150.0, 300.0, 600.0,
+    1_200.0, 1_800.0, 2_400.0, 3_600.0, 4_800.0, 7_200.0, 9_600.0, 19_200.0,
+];
+
+/// A 6551 Asynchronous Communications Interface Adapter, with its baud rate generator
+/// clocked from the CPU clock frequency.
+#[derive(Clone, Copy, Debug)]
+pub struct W65C51 {
+    // Transmit Data Register, and the shift register sending the previous byte
+    tdr: u8,
+    tsr: u8,
+    // Cycles left before the byte in the shift register is fully sent, 0 when idle
+    tx_cycles_left: usize,
+    rdr: u8,
+    rsr: u8,
+    // Cycles left before the byte being received is complete, 0 when idle
+    rx_cycles_left: usize,
+    status: u8,
+    command: u8,
+    control: u8,
+    clock_freq: usize,
+    // The WDC W65C51N always reads TDRE as 1 and never triggers a transmit interrupt:
+    // writing the TDR sends it right away, even if a byte is already going out
+    xmit_bug: bool,
+}
+
+impl W65C51 {
+    pub fn new(clock_freq: usize, xmit_bug: bool) -> W65C51 {
+        // Values after a hardware reset, according to the datasheet
+        W65C51 {
+            tdr: 0x00,
+            tsr: 0x00,
+            tx_cycles_left: 0,
+            rdr: 0x00,
+            rsr: 0x00,
+            rx_cycles_left: 0,
+            status: STATUS_TDRE,
+            command: 0b0000_0010,
+            control: 0x00,
+            clock_freq,
+            xmit_bug,
+        }
+    }
+
+    // Bits of a frame: start bit, data bits, parity bit and stop bits
+    fn frame_bits(&self) -> f64 {
+        let data_bits = 8 - ((self.control >> 5) & 0b11);
+        let parity_bits = (self.command >> 5) & 0b1;
+        let stop_bits = match (self.control & 0b1000_0000 != 0, data_bits, parity_bits) {
+            (false, _, _) => 1.0,
+            // SBN set gives 2 stop bits, except with 8 data bits and a parity bit, 
+            // and with 5 data bits and no parity bit
+            (true, 8, 1) => 1.0,
+            (true, 5, 0) => 1.5,
+            (true, _, _) => 2.0,
+        };
+        (1 + data_bits + parity_bits) as f64 + stop_bits
+    }
+
+    fn frame_cycles(&self) -> usize {
+        let baud_rate = BAUD_RATES[(self.control & 0x0f) as usize];
+        ((self.frame_bits() * self.clock_freq as f64 / baud_rate).round() as usize).max(1)
+    }
+
+    // The bits of the data register actually sent or received, depending on the word length
+    fn word_mask(&self) -> u8 {
+        0xff >> ((self.control >> 5) & 0b11)
+    }
+
+    // DTR low (command bit 0 set) enables the receiver and every interrupt
+    fn is_enabled(&self) -> bool {
+        self.command & 0b0000_0001 != 0
+    }
+
+    fn transmitter_enabled(&self) -> bool {
+        matches!((self.command >> 2) & 0b11, 0b01 | 0b10)
+    }
+
+    fn tx_interrupt_enabled(&self) -> bool {
+        self.is_enabled() && (self.command >> 2) & 0b11 == 0b01 && !self.xmit_bug
+    }
+
+    fn rx_interrupt_enabled(&self) -> bool {
+        self.is_enabled() && self.command & 0b0000_0010 == 0
+    }
+
+    fn echo_mode(&self) -> bool {
+        self.command & 0b0001_0000 != 0
+    }
+
+    fn set_irq<S: AciaSystem>(&mut self, acia_system: &mut S) {
+        if self.status & STATUS_IRQ == 0 {
+            self.status |= STATUS_IRQ;
+            acia_system.update_irq(self, true);
+        }
+    }
+
+    // Move the TDR to the shift register, once the previous byte went out
+    fn start_transmission<S: AciaSystem>(&mut self, acia_system: &mut S) {
+        self.tsr = self.tdr & self.word_mask();
+        self.tx_cycles_left = self.frame_cycles();
+        self.status |= STATUS_TDRE;
+        if self.tx_interrupt_enabled() {
+            self.set_irq(acia_system);
+        }
+    }
+
+    /// To call on PHI2 falling edge, *before* calling `read` or `write`
+    pub fn clock_pulse<S: AciaSystem>(&mut self, acia_system: &mut S) {
+        if self.tx_cycles_left > 0 {
+            self.tx_cycles_left -= 1;
+            if self.tx_cycles_left == 0 {
+                acia_system.transmit_byte(self, self.tsr);
+            }
+        }
+        if self.tx_cycles_left == 0 && self.status & STATUS_TDRE == 0 && self.transmitter_enabled() {
+            self.start_transmission(acia_system);
+        }
+
+        if self.rx_cycles_left > 0 {
+            self.rx_cycles_left -= 1;
+            if self.rx_cycles_left == 0 {
+                // The byte is lost if the previous one wasn't read
+                if self.status & STATUS_RDRF != 0 {
+                    self.status |= STATUS_OVERRUN;
+                } else {
+                    self.rdr = self.rsr;
+                    self.status |= STATUS_RDRF;
+                }
+                if self.echo_mode() {
+                    acia_system.transmit_byte(self, self.rsr);
+                }
+                if self.rx_interrupt_enabled() {
+                    self.set_irq(acia_system);
+                }
+            }
+        }
+        if self.rx_cycles_left == 0 && self.is_enabled() {
+            if let Some(byte) = acia_system.receive_byte(self) {
+                self.rsr = byte & self.word_mask();
+                self.rx_cycles_left = self.frame_cycles();
+            }
+        }
+    }
+
+    pub fn read<S: AciaSystem>(&mut self, acia_system: &mut S, register_select: u8) -> u8 {
+        match register_select & 0b11 {
+            DATA => {
+                self.status &= !(STATUS_RDRF | STATUS_OVERRUN);
+                self.rdr
+            },
+            STATUS => {
+                let status = match self.xmit_bug {
+                    true => self.status | STATUS_TDRE,
+                    false => self.status,
+                };
+                // Reading the status register clears the interrupt
+                if self.status & STATUS_IRQ != 0 {
+                    self.status &= !STATUS_IRQ;
+                    acia_system.update_irq(self, false);
+                }
+                status
+            },
+            COMMAND => self.command,
+            CONTROL => self.control,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn write<S: AciaSystem>(&mut self, acia_system: &mut S, register_select: u8, value: u8) {
+        match register_select & 0b11 {
+            DATA => {
+                if self.xmit_bug && self.tx_cycles_left > 0 {
+                    acia_system.warn(self, &format!("TDR written while ${:02x} was still being sent, \
+                        the W65C51N would garble it", self.tsr));
+                    // The new byte goes out right away, the rest of the previous one is lost
+                    self.tx_cycles_left = 0;
+                }
+                self.tdr = value;
+                self.status &= !STATUS_TDRE;
+                if self.tx_cycles_left == 0 && self.transmitter_enabled() {
+                    self.start_transmission(acia_system);
+                }
+            },
+            // Programmed reset: the data doesn't matter
+            STATUS => {
+                self.command = (self.command & 0b1110_0000) | 0b0000_0010;
+                self.status &= !STATUS_OVERRUN;
+            },
+            COMMAND => {
+                self.command = value;
+                if self.status & STATUS_TDRE != 0 && self.tx_cycles_left == 0 && self.tx_interrupt_enabled() {
+                    self.set_irq(acia_system);
+                }
+            },
+            CONTROL => self.control = value,
+            _ => unreachable!(),
+        }
+
+        // DTR high disables the interrupts
+        if !self.is_enabled() && self.status & STATUS_IRQ != 0 {
+            self.status &= !STATUS_IRQ;
+            acia_system.update_irq(self, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    // Lets the tests play the other end of the serial line
+    #[derive(Default)]
+    struct TestAciaSystem {
+        to_receive: VecDeque<u8>,
+        transmitted: Vec<u8>,
+        irq: bool,
+        warnings: Vec<String>,
+    }
+
+    impl AciaSystem for TestAciaSystem {
+        fn receive_byte(&mut self, _acia: &mut W65C51) -> Option<u8> {
+            self.to_receive.pop_front()
+        }
+
+        fn transmit_byte(&mut self, _acia: &mut W65C51, byte: u8) {
+            self.transmitted.push(byte);
+        }
+
+        fn update_irq(&mut self, _acia: &mut W65C51, irq: bool) {
+            self.irq = irq;
+        }
+
+        fn warn(&mut self, _acia: &mut W65C51, msg: &str) {
+            self.warnings.push(String::from(msg));
+        }
+    }
+
+    fn clock(acia: &mut W65C51, sys: &mut TestAciaSystem, cycles: usize) {
+        for _ in 0..cycles {
+            acia.clock_pulse(sys);
+        }
+    }
+
+    #[test]
+    fn frame_timing() {
+        let mut acia = W65C51::new(1_000_000, false);
+
+        // 19200 bauds, 8N1: 10 bits of 52.08 cycles
+        acia.write(&mut TestAciaSystem::default(), CONTROL, 0b0001_1111);
+        assert_eq!(521, acia.frame_cycles());
+        // 9600 bauds, 7 bits, even parity, 2 stop bits
+        acia.write(&mut TestAciaSystem::default(), CONTROL, 0b1010_1110);
+        acia.write(&mut TestAciaSystem::default(), COMMAND, 0b0110_1011);
+        assert_eq!(1_146, acia.frame_cycles());
+        assert_eq!(0x7f, acia.word_mask());
+        // 9600 bauds, 8 bits, even parity and SBN set: only 1 stop bit
+        acia.write(&mut TestAciaSystem::default(), CONTROL, 0b1000_1110);
+        assert_eq!(1_146, acia.frame_cycles());
+        // 9600 bauds, 5 bits, no parity and SBN set: 1.5 stop bits
+        acia.write(&mut TestAciaSystem::default(), CONTROL, 0b1110_1110);
+        acia.write(&mut TestAciaSystem::default(), COMMAND, 0b0000_1011);
+        assert_eq!(781, acia.frame_cycles());
+    }
+
+    #[test]
+    fn transmit() {
+        let mut sys = TestAciaSystem::default();
+        let mut acia = W65C51::new(1_000_000, false);
+        acia.write(&mut sys, CONTROL, 0b0001_1111);
+        // Transmit interrupt enabled, receive interrupt disabled
+        acia.write(&mut sys, COMMAND, 0b0000_0111);
+        assert!(sys.irq, "The empty TDR should trigger an interrupt as soon as it is enabled");
+        assert_eq!(STATUS_IRQ | STATUS_TDRE, acia.read(&mut sys, STATUS));
+        assert!(!sys.irq);
+
+        acia.write(&mut sys, DATA, b'H');
+        assert!(sys.irq, "The TDR goes straight to the idle shift register");
+        acia.read(&mut sys, STATUS);
+        acia.write(&mut sys, DATA, b'i');
+        assert_eq!(0, acia.read(&mut sys, STATUS) & STATUS_TDRE);
+
+        clock(&mut acia, &mut sys, 520);
+        assert_eq!(Vec::<u8>::new(), sys.transmitted);
+        clock(&mut acia, &mut sys, 1);
+        assert_eq!(b"H".to_vec(), sys.transmitted);
+        assert_eq!(STATUS_IRQ | STATUS_TDRE, acia.read(&mut sys, STATUS), "The i moved to the shift register");
+        clock(&mut acia, &mut sys, 521);
+        assert_eq!(b"Hi".to_vec(), sys.transmitted);
+    }
+
+    #[test]
+    fn xmit_bug() {
+        let mut sys = TestAciaSystem::default();
+        let mut acia = W65C51::new(1_000_000, true);
+        acia.write(&mut sys, CONTROL, 0b0001_1111);
+        acia.write(&mut sys, COMMAND, 0b0000_0111);
+        assert!(!sys.irq, "No transmit interrupt on the W65C51N");
+
+        acia.write(&mut sys, DATA, b'H');
+        clock(&mut acia, &mut sys, 100);
+        assert_eq!(STATUS_TDRE, acia.read(&mut sys, STATUS), "TDRE always reads 1");
+        acia.write(&mut sys, DATA, b'i');
+        assert_eq!(1, sys.warnings.len());
+        clock(&mut acia, &mut sys, 521);
+        assert_eq!(b"i".to_vec(), sys.transmitted);
+    }
+
+    #[test]
+    fn receive() {
+        let mut sys = TestAciaSystem::default();
+        sys.to_receive.extend(b"ok!");
+        let mut acia = W65C51::new(1_000_000, false);
+        acia.write(&mut sys, CONTROL, 0b0001_1111);
+        clock(&mut acia, &mut sys, 2_000);
+        assert_eq!(3, sys.to_receive.len(), "Nothing is received while DTR is high");
+
+        // Receive interrupt enabled, transmitter off
+        acia.write(&mut sys, COMMAND, 0b0000_0001);
+        clock(&mut acia, &mut sys, 521);
+        assert!(!sys.irq);
+        clock(&mut acia, &mut sys, 1);
+        assert!(sys.irq);
+        assert_eq!(STATUS_IRQ | STATUS_TDRE | STATUS_RDRF, acia.read(&mut sys, STATUS));
+        assert_eq!(b'o', acia.read(&mut sys, DATA));
+        assert_eq!(STATUS_TDRE, acia.read(&mut sys, STATUS));
+
+        // The ! arrives before the k is read
+        clock(&mut acia, &mut sys, 1_042);
+        assert_eq!(STATUS_IRQ | STATUS_TDRE | STATUS_RDRF | STATUS_OVERRUN, acia.read(&mut sys, STATUS));
+        assert_eq!(b'k', acia.read(&mut sys, DATA));
+        assert_eq!(STATUS_TDRE, acia.read(&mut sys, STATUS));
+    }
+
+    #[test]
+    fn echo_and_reset() {
+        let mut sys = TestAciaSystem::default();
+        sys.to_receive.extend(b"e");
+        let mut acia = W65C51::new(1_000_000, false);
+        acia.write(&mut sys, CONTROL, 0b0001_1111);
+        acia.write(&mut sys, COMMAND, 0b0001_0011);
+        clock(&mut acia, &mut sys, 522);
+        assert_eq!(b"e".to_vec(), sys.transmitted);
+
+        // Programmed reset
+        acia.write(&mut sys, STATUS, 0x00);
+        assert_eq!(0b0000_0010, acia.read(&mut sys, COMMAND));
+        assert_eq!(0b0001_1111, acia.read(&mut sys, CONTROL));
+    }
+}
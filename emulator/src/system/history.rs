@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use std::fmt;
+use super::OPCODES;
+
+// Number of executed instructions kept for crash dumps
+pub const HISTORY_LEN: usize = 4096;
+
+/// One executed instruction, with the CPU registers as they were when its opcode was fetched.
+#[derive(Clone, Copy)]
+pub struct InstrRecord {
+    pub step: usize,
+    pub cycle: usize,
+    pub pc: u16,
+    pub opcode: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub s: u8,
+    pub p: u8,
+}
+
+impl fmt::Display for InstrRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Same flag letters as the 65C02 datasheet, lowercase when the flag is clear
+        let flags: String = "NV1BDIZC".chars().enumerate().map(|(i, c)| {
+            if self.p & (0b1000_0000 >> i) != 0 { c } else { c.to_ascii_lowercase() }
+        }).collect();
+
+        write!(f, "{:>8} {:>10}  {:04x}: {:02x} {:<20} A={:02x} X={:02x} Y={:02x} S={:02x} P={}",
+            self.step, self.cycle, self.pc, self.opcode, OPCODES[self.opcode as usize],
+            self.a, self.x, self.y, self.s, flags)
+    }
+}
+
+/// Ring buffer holding the last `HISTORY_LEN` executed instructions.
+///
+/// It is always filled, whether logging is enabled or not,
+/// so that a crash dump can be written after the fact.
+pub struct InstrHistory {
+    records: VecDeque<InstrRecord>,
+}
+
+impl InstrHistory {
+    pub fn new() -> InstrHistory {
+        InstrHistory {
+            records: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    pub fn push(&mut self, record: InstrRecord) {
+        if self.records.len() == HISTORY_LEN {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// Iterate over the recorded instructions, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &InstrRecord> {
+        self.records.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(step: usize) -> InstrRecord {
+        InstrRecord { step, cycle: step * 2, pc: 0x8000, opcode: 0xea, a: 0, x: 0, y: 0, s: 0xff, p: 0b0011_0000 }
+    }
+
+    #[test]
+    fn ring_buffer_wrap() {
+        let mut history = InstrHistory::new();
+        for step in 0..HISTORY_LEN {
+            history.push(record(step));
+        }
+        assert_eq!(HISTORY_LEN, history.iter().count());
+        assert_eq!(0, history.iter().next().unwrap().step);
+
+        // Once full, each new instruction drops the oldest one
+        history.push(record(HISTORY_LEN));
+        history.push(record(HISTORY_LEN + 1));
+        assert_eq!(HISTORY_LEN, history.iter().count());
+        assert_eq!(2, history.iter().next().unwrap().step);
+        assert_eq!(HISTORY_LEN + 1, history.iter().last().unwrap().step);
+    }
+
+    #[test]
+    fn record_display() {
+        let record = InstrRecord { 
+            step: 12, cycle: 345, pc: 0x8004, opcode: 0xa9, a: 0x01, x: 0x02, y: 0xff, s: 0xfd, p: 0b1010_0001,
+        };
+        assert_eq!("      12        345  8004: a9 LDA imm              A=01 X=02 Y=ff S=fd P=Nv1bdizC", 
+            record.to_string());
+    }
+}
@@ -0,0 +1,374 @@
+use std::fmt;
+use super::wiring::InputPin;
+use super::keyboard::{Ps2Keyboard, Key};
+use super::us_to_cycles;
+
+// Default time during which a push-button contact bounces after being pressed or released, in microseconds
+pub const DEFAULT_BOUNCE_TIME: usize = 2_000;
+// Time between two phase changes when the rotary encoder turns, in microseconds
+const ENCODER_PHASE_TIME: usize = 1_000;
+// Number of push-buttons and DIP switches
+pub const INPUT_COUNT: usize = 8;
+
+/// Something the user does with the input devices, from a frontend or the CLI.
+///
+/// The text form, used by every frontend, is one of:
+/// ```text
+/// sw 3 on      # close the DIP switch SW.3 (off to open it)
+/// press 0      # press the push-button BTN.0
+/// release 0
+/// turn 2       # turn the rotary encoder 2 detents clockwise (negative for counterclockwise)
+/// key enter    # press and release a key of the PS/2 keyboard (see Key::parse for the names)
+/// keydown shift
+/// keyup shift
+/// type Hello!  # type the rest of the line on the keyboard, as is
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub enum InputCommand {
+    Switch(u8, bool),
+    Press(u8),
+    Release(u8),
+    Turn(i32),
+    Key(Key),
+    KeyDown(Key),
+    KeyUp(Key),
+    Type(String),
+}
+
+impl InputCommand {
+    pub fn parse(text: &str) -> Result<InputCommand, String> {
+        // The text to type keeps its case and spaces
+        let trimmed = text.trim_start();
+        match trimmed.split_once(' ') {
+            Some((word, text)) if word.eq_ignore_ascii_case("type") && !text.is_empty() => {
+                return match text.chars().find(|&c| Key::for_char(c).is_none()) {
+                    Some(c) => Err(format!("no key to type '{}' on", c)),
+                    None => Ok(InputCommand::Type(String::from(text))),
+                };
+            },
+            _ => {},
+        }
+
+        let words: Vec<String> = text.split_whitespace().map(|word| word.to_ascii_lowercase()).collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let input = |nb: &str| match nb.parse::<u8>() {
+            Ok(nb) if (nb as usize) < INPUT_COUNT => Ok(nb),
+            _ => Err(format!("invalid input number \"{}\" (expected 0 to {})", nb, INPUT_COUNT - 1)),
+        };
+        let key = |name: &str| Key::parse(name).ok_or_else(|| format!("unknown key \"{}\"", name));
+
+        match words.as_slice() {
+            ["sw", nb, "on"] => Ok(InputCommand::Switch(input(nb)?, true)),
+            ["sw", nb, "off"] => Ok(InputCommand::Switch(input(nb)?, false)),
+            ["press", nb] => Ok(InputCommand::Press(input(nb)?)),
+            ["release", nb] => Ok(InputCommand::Release(input(nb)?)),
+            ["turn", detents] => match detents.trim_start_matches('+').parse::<i32>() {
+                Ok(detents) => Ok(InputCommand::Turn(detents)),
+                Err(_) => Err(format!("invalid number of detents \"{}\"", detents)),
+            },
+            ["key", name] => Ok(InputCommand::Key(key(name)?)),
+            ["keydown", name] => Ok(InputCommand::KeyDown(key(name)?)),
+            ["keyup", name] => Ok(InputCommand::KeyUp(key(name)?)),
+            _ => Err(format!("unknown input command \"{}\" \
+                (expected \"sw <n> on|off\", \"press <n>\", \"release <n>\", \"turn <detents>\", \
+                \"key <name>\", \"keydown <name>\", \"keyup <name>\" or \"type <text>\")", text.trim())),
+        }
+    }
+}
+
+impl fmt::Display for InputCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputCommand::Switch(nb, true) => write!(f, "sw {} on", nb),
+            InputCommand::Switch(nb, false) => write!(f, "sw {} off", nb),
+            InputCommand::Press(nb) => write!(f, "press {}", nb),
+            InputCommand::Release(nb) => write!(f, "release {}", nb),
+            InputCommand::Turn(detents) => write!(f, "turn {}", detents),
+            InputCommand::Key(key) => write!(f, "key {}", key.name()),
+            InputCommand::KeyDown(key) => write!(f, "keydown {}", key.name()),
+            InputCommand::KeyUp(key) => write!(f, "keyup {}", key.name()),
+            InputCommand::Type(text) => write!(f, "type {}", text),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Button {
+    pressed: bool,
+    // Cycles on which the contact changes while bouncing, always an odd number of them
+    bounce_edges: Vec<usize>,
+}
+
+/// The DIP switches, push-buttons, rotary encoder and keyboard the user plays with.
+///
+/// Every contact connects its net to the ground when closed, so the pins they are wired to
+/// need a pull-up resistor, and read low when the switch is on or the button is pressed.
+pub struct Inputs {
+    // One bit per DIP switch, set when it is closed
+    switches: u8,
+    buttons: Vec<Button>,
+    // Position in the quadrature sequence, see encoder_contacts
+    encoder_phase: u8,
+    // Phase changes the encoder still has to go through, negative when turning counterclockwise
+    encoder_pending: i32,
+    next_encoder_change: usize,
+    bounce_cycles: usize,
+    encoder_phase_cycles: usize,
+    keyboard: Ps2Keyboard,
+    cycle: usize,
+}
+
+impl Inputs {
+    /// `bounce_time` is in microseconds, 0 giving perfect buttons.
+    pub fn new(clock_freq: usize, bounce_time: usize) -> Inputs {
+        Inputs {
+            switches: 0,
+            buttons: vec![Button::default(); INPUT_COUNT],
+            encoder_phase: 0,
+            encoder_pending: 0,
+            next_encoder_change: 0,
+            bounce_cycles: us_to_cycles(bounce_time, clock_freq),
+            encoder_phase_cycles: us_to_cycles(ENCODER_PHASE_TIME, clock_freq).max(1),
+            keyboard: Ps2Keyboard::new(clock_freq),
+            cycle: 0,
+        }
+    }
+
+    /// Act on the inputs, the changes start on the next cycle.
+    pub fn apply(&mut self, command: InputCommand) {
+        match command {
+            InputCommand::Switch(nb, closed) => self.switches = match closed {
+                true => self.switches | (1 << nb),
+                false => self.switches & !(1 << nb),
+            },
+            InputCommand::Press(nb) => self.set_button(nb, true),
+            InputCommand::Release(nb) => self.set_button(nb, false),
+            InputCommand::Turn(detents) => {
+                if self.encoder_pending == 0 {
+                    self.next_encoder_change = self.cycle + 1;
+                }
+                // 4 phase changes per detent, no one turns the knob more than i32::MAX / 4 detents
+                self.encoder_pending = self.encoder_pending.saturating_add(detents.saturating_mul(4));
+            },
+            InputCommand::Key(key) => {
+                self.keyboard.key_down(key);
+                self.keyboard.key_up(key);
+            },
+            InputCommand::KeyDown(key) => self.keyboard.key_down(key),
+            InputCommand::KeyUp(key) => self.keyboard.key_up(key),
+            InputCommand::Type(text) => self.keyboard.type_text(&text),
+        }
+    }
+
+    fn set_button(&mut self, nb: u8, pressed: bool) {
+        let bounce_cycles = self.bounce_cycles;
+        let start = self.cycle + 1;
+        let button = &mut self.buttons[nb as usize];
+        if button.pressed == pressed {
+            return;
+        }
+
+        button.pressed = pressed;
+        button.bounce_edges = vec![start];
+        if bounce_cycles > 0 {
+            // Deterministic pseudo-random bounces, so that the same inputs on the same cycles
+            // always give the same levels
+            let mut seed = ((start as u32).wrapping_mul(2_654_435_761) ^ ((nb as u32) << 24)) | 1;
+            let mut next_random = || {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as usize
+            };
+
+            let edge_count = 3 + 2 * (next_random() % 4);
+            for _ in 1..edge_count {
+                let edge = start + 1 + next_random() % bounce_cycles;
+                button.bounce_edges.push(edge);
+            }
+            button.bounce_edges.sort_unstable();
+        }
+    }
+
+    /// Advance the inputs to `cycle`, the cycle count of the system.
+    pub fn clock(&mut self, cycle: usize) {
+        self.cycle = cycle;
+
+        if self.encoder_pending != 0 && cycle >= self.next_encoder_change {
+            let direction = self.encoder_pending.signum();
+            self.encoder_phase = (self.encoder_phase as i32 + direction).rem_euclid(4) as u8;
+            self.encoder_pending -= direction;
+            self.next_encoder_change = cycle + self.encoder_phase_cycles;
+        }
+
+        for button in &mut self.buttons {
+            if button.bounce_edges.iter().all(|&edge| edge <= cycle) {
+                button.bounce_edges.clear();
+            }
+        }
+
+        self.keyboard.clock(cycle);
+    }
+
+    // Whether the A and B contacts of the encoder are closed, A closing first when turning clockwise
+    fn encoder_contacts(&self) -> (bool, bool) {
+        match self.encoder_phase {
+            0 => (false, false),
+            1 => (true, false),
+            2 => (true, true),
+            _ => (false, true),
+        }
+    }
+
+    /// Whether the contact of an input connects its pin to the ground right now,
+    /// or for the keyboard, whether it pulls the line low.
+    pub fn is_closed(&self, pin: InputPin) -> bool {
+        match pin {
+            InputPin::Switch(nb) => self.switches & (1 << nb) != 0,
+            InputPin::Button(nb) => {
+                let button = &self.buttons[nb as usize];
+                let edges_left = button.bounce_edges.iter().filter(|&&edge| edge > self.cycle).count();
+                // The contact goes back and forth between the old and new states until the last edge
+                button.pressed ^ (edges_left % 2 == 1)
+            },
+            InputPin::EncoderA => self.encoder_contacts().0,
+            InputPin::EncoderB => self.encoder_contacts().1,
+            InputPin::KeyboardClock => self.keyboard.is_clock_low(),
+            InputPin::KeyboardData => self.keyboard.is_data_low(),
+        }
+    }
+
+    /// Whether any contact is closed, in which case it may fight a VIA output.
+    pub fn any_closed(&self) -> bool {
+        self.switches != 0
+            || self.encoder_phase != 0
+            || self.keyboard.is_clock_low()
+            || self.keyboard.is_data_low()
+            || (0..INPUT_COUNT as u8).any(|nb| self.is_closed(InputPin::Button(nb)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_until(inputs: &mut Inputs, cycle: usize) {
+        while inputs.cycle < cycle {
+            let next_cycle = inputs.cycle + 1;
+            inputs.clock(next_cycle);
+        }
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Ok(InputCommand::Switch(3, true)), InputCommand::parse("sw 3 on"));
+        assert_eq!(Ok(InputCommand::Switch(7, false)), InputCommand::parse("  SW 7   Off "));
+        assert_eq!(Ok(InputCommand::Press(0)), InputCommand::parse("press 0"));
+        assert_eq!(Ok(InputCommand::Release(2)), InputCommand::parse("release 2"));
+        assert_eq!(Ok(InputCommand::Turn(2)), InputCommand::parse("turn +2"));
+        assert_eq!(Ok(InputCommand::Turn(-1)), InputCommand::parse("turn -1"));
+
+        assert_eq!(Err(String::from("invalid input number \"8\" (expected 0 to 7)")), InputCommand::parse("press 8"));
+        assert_eq!(Err(String::from("invalid number of detents \"a\"")), InputCommand::parse("turn a"));
+        assert!(InputCommand::parse("sw 1 maybe").unwrap_err().starts_with("unknown input command \"sw 1 maybe\""));
+
+        assert_eq!(Ok(InputCommand::Key(Key::parse("enter").unwrap())), InputCommand::parse("key Enter"));
+        assert_eq!(Ok(InputCommand::KeyUp(Key::parse("shift").unwrap())), InputCommand::parse("keyup shift"));
+        assert_eq!(Ok(InputCommand::Type(String::from("Hello,  World!"))), InputCommand::parse("  TYPE Hello,  World!"));
+        assert_eq!(Err(String::from("unknown key \"hyper\"")), InputCommand::parse("keydown hyper"));
+        assert_eq!(Err(String::from("no key to type 'é' on")), InputCommand::parse("type café"));
+        // Multibyte characters where a command name is expected
+        assert!(InputCommand::parse("abcdé").unwrap_err().starts_with("unknown input command \"abcdé\""));
+        assert!(InputCommand::parse("typé x").unwrap_err().starts_with("unknown input command \"typé x\""));
+
+        for command in &["sw 3 on", "sw 4 off", "press 1", "release 6", "turn -3", "key f1", "keydown a", "keyup up", "type Hi"] {
+            assert_eq!(*command, InputCommand::parse(command).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn switches() {
+        let mut inputs = Inputs::new(1_000_000, DEFAULT_BOUNCE_TIME);
+        assert!(!inputs.any_closed());
+
+        inputs.apply(InputCommand::Switch(5, true));
+        assert!(inputs.is_closed(InputPin::Switch(5)));
+        assert!(!inputs.is_closed(InputPin::Switch(4)));
+        assert!(inputs.any_closed());
+
+        inputs.apply(InputCommand::Switch(5, false));
+        assert!(!inputs.any_closed());
+    }
+
+    #[test]
+    fn button_bounce() {
+        let mut inputs = Inputs::new(1_000_000, 1_000);
+        run_until(&mut inputs, 100);
+        inputs.apply(InputCommand::Press(2));
+        assert!(!inputs.is_closed(InputPin::Button(2)), "The press starts on the next cycle");
+
+        let levels: Vec<bool> = (101..=1_102).map(|cycle| {
+            run_until(&mut inputs, cycle);
+            inputs.is_closed(InputPin::Button(2))
+        }).collect();
+        assert!(levels[0], "The contact closes first");
+        let changes = levels.windows(2).filter(|pair| pair[0] != pair[1]).count();
+        assert!(changes >= 2 && changes % 2 == 0, "The contact should bounce, then settle closed ({} changes)", changes);
+        assert!(levels[1_000..].iter().all(|&closed| closed));
+
+        // The same press on the same cycle always bounces the same way
+        let mut other_inputs = Inputs::new(1_000_000, 1_000);
+        run_until(&mut other_inputs, 100);
+        other_inputs.apply(InputCommand::Press(2));
+        let other_levels: Vec<bool> = (101..=1_102).map(|cycle| {
+            run_until(&mut other_inputs, cycle);
+            other_inputs.is_closed(InputPin::Button(2))
+        }).collect();
+        assert_eq!(levels, other_levels);
+
+        inputs.apply(InputCommand::Release(2));
+        run_until(&mut inputs, 3_000);
+        assert!(!inputs.any_closed());
+    }
+
+    #[test]
+    fn button_without_bounce() {
+        let mut inputs = Inputs::new(1_000_000, 0);
+        inputs.apply(InputCommand::Press(0));
+        run_until(&mut inputs, 1);
+        assert!(inputs.is_closed(InputPin::Button(0)));
+        inputs.apply(InputCommand::Release(0));
+        run_until(&mut inputs, 2);
+        assert!(!inputs.is_closed(InputPin::Button(0)));
+    }
+
+    #[test]
+    fn encoder_quadrature() {
+        let mut inputs = Inputs::new(1_000_000, DEFAULT_BOUNCE_TIME);
+        let contacts = |inputs: &Inputs| (inputs.is_closed(InputPin::EncoderA), inputs.is_closed(InputPin::EncoderB));
+
+        inputs.apply(InputCommand::Turn(1));
+        let mut sequence = vec![contacts(&inputs)];
+        for step in 0..4 {
+            run_until(&mut inputs, 1 + step * 1_000);
+            sequence.push(contacts(&inputs));
+        }
+        assert_eq!(vec![(false, false), (true, false), (true, true), (false, true), (false, false)], sequence,
+            "A should lead B when turning clockwise");
+
+        inputs.apply(InputCommand::Turn(-1));
+        let mut sequence = Vec::new();
+        for step in 1..=4 {
+            run_until(&mut inputs, 3_001 + step * 1_000);
+            sequence.push(contacts(&inputs));
+        }
+        assert_eq!(vec![(false, true), (true, true), (true, false), (false, false)], sequence,
+            "B should lead A when turning counterclockwise");
+        assert!(!inputs.any_closed());
+
+        // Huge turns saturate instead of overflowing
+        inputs.apply(InputCommand::parse("turn 600000000").unwrap());
+        inputs.apply(InputCommand::Turn(i32::MAX));
+        assert_eq!(i32::MAX, inputs.encoder_pending);
+    }
+}
@@ -0,0 +1,70 @@
+/// The wired-OR `IRQB` line of the CPU.
+///
+/// Each device able to trigger an interrupt is a source of the line,
+/// and the line is asserted (electrically low) as long as at least one of them asserts it.
+pub struct IrqLine {
+    names: Vec<String>,
+    asserted: Vec<bool>,
+}
+
+impl IrqLine {
+    pub fn new() -> IrqLine {
+        IrqLine {
+            names: Vec::new(),
+            asserted: Vec::new(),
+        }
+    }
+
+    /// Connect a new device to the line, and get the number to use with `set`.
+    pub fn add_source(&mut self, name: &str) -> usize {
+        self.names.push(String::from(name));
+        self.asserted.push(false);
+        self.asserted.len() - 1
+    }
+
+    /// Update the *logical* IRQ output of a source, `true` meaning it is pulling the line low.
+    pub fn set(&mut self, source: usize, irq: bool) {
+        self.asserted[source] = irq;
+    }
+
+    /// The *logical* level of the line, which can be sent to the `set_irq` function of the processor.
+    pub fn is_asserted(&self) -> bool {
+        self.asserted.iter().any(|&irq| irq)
+    }
+
+    /// Names of the devices currently asserting the line.
+    pub fn asserting_sources(&self) -> Vec<&str> {
+        self.names.iter()
+            .zip(self.asserted.iter())
+            .filter(|(_, &irq)| irq)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wired_or() {
+        let mut irq_line = IrqLine::new();
+        let via_0 = irq_line.add_source("VIA $6000");
+        let via_1 = irq_line.add_source("VIA $5000");
+        assert!(!irq_line.is_asserted());
+
+        irq_line.set(via_0, true);
+        irq_line.set(via_1, true);
+        assert!(irq_line.is_asserted());
+        assert_eq!(vec!["VIA $6000", "VIA $5000"], irq_line.asserting_sources());
+
+        // One device releasing the line doesn't hide the other one's interrupt
+        irq_line.set(via_0, false);
+        assert!(irq_line.is_asserted());
+        assert_eq!(vec!["VIA $5000"], irq_line.asserting_sources());
+
+        irq_line.set(via_1, false);
+        assert!(!irq_line.is_asserted());
+        assert!(irq_line.asserting_sources().is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::VecDeque;
+use super::us_to_cycles;
+
+// Period of the clock generated by the keyboard (12.5 kHz), in microseconds
+const CLOCK_PERIOD: usize = 80;
+// Idle time between two bytes, in microseconds
+const BYTE_GAP: usize = 200;
+
+const EXTENDED_PREFIX: u8 = 0xe0;
+const BREAK_PREFIX: u8 = 0xf0;
+
+// (name, scan code set 2, needs the E0 prefix)
+const KEYS: [(&str, u8, bool); 79] = [
+    ("a", 0x1c, false), ("b", 0x32, false), ("c", 0x21, false), ("d", 0x23, false),
+    ("e", 0x24, false), ("f", 0x2b, false), ("g", 0x34, false), ("h", 0x33, false),
+    ("i", 0x43, false), ("j", 0x3b, false), ("k", 0x42, false), ("l", 0x4b, false),
+    ("m", 0x3a, false), ("n", 0x31, false), ("o", 0x44, false), ("p", 0x4d, false),
+    ("q", 0x15, false), ("r", 0x2d, false), ("s", 0x1b, false), ("t", 0x2c, false),
+    ("u", 0x3c, false), ("v", 0x2a, false), ("w", 0x1d, false), ("x", 0x22, false),
+    ("y", 0x35, false), ("z", 0x1a, false),
+    ("0", 0x45, false), ("1", 0x16, false), ("2", 0x1e, false), ("3", 0x26, false),
+    ("4", 0x25, false), ("5", 0x2e, false), ("6", 0x36, false), ("7", 0x3d, false),
+    ("8", 0x3e, false), ("9", 0x46, false),
+    ("`", 0x0e, false), ("-", 0x4e, false), ("=", 0x55, false), ("\\", 0x5d, false),
+    ("[", 0x54, false), ("]", 0x5b, false), (";", 0x4c, false), ("'", 0x52, false),
+    (",", 0x41, false), (".", 0x49, false), ("/", 0x4a, false),
+    ("space", 0x29, false), ("enter", 0x5a, false), ("backspace", 0x66, false), ("tab", 0x0d, false),
+    ("esc", 0x76, false), ("capslock", 0x58, false),
+    ("shift", 0x12, false), ("rshift", 0x59, false), ("ctrl", 0x14, false), ("alt", 0x11, false),
+    ("f1", 0x05, false), ("f2", 0x06, false), ("f3", 0x04, false), ("f4", 0x0c, false),
+    ("f5", 0x03, false), ("f6", 0x0b, false), ("f7", 0x83, false), ("f8", 0x0a, false),
+    ("f9", 0x01, false), ("f10", 0x09, false), ("f11", 0x78, false), ("f12", 0x07, false),
+    ("up", 0x75, true), ("down", 0x72, true), ("left", 0x6b, true), ("right", 0x74, true),
+    ("home", 0x6c, true), ("end", 0x69, true), ("insert", 0x70, true), ("delete", 0x71, true),
+    ("pageup", 0x7d, true), ("pagedown", 0x7a, true),
+];
+
+// The characters typed with shift, and the key they are on
+const SHIFTED_CHARS: [(char, &str); 21] = [
+    ('!', "1"), ('@', "2"), ('#', "3"), ('$', "4"), ('%', "5"), ('^', "6"), ('&', "7"),
+    ('*', "8"), ('(', "9"), (')', "0"), ('_', "-"), ('+', "="), ('{', "["), ('}', "]"),
+    ('|', "\\"), (':', ";"), ('"', "'"), ('<', ","), ('>', "."), ('?', "/"), ('~', "`"),
+];
+
+/// A key of the keyboard, see `Key::parse` for the names.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Key(usize);
+
+impl Key {
+    /// The character on the key for letters, digits and punctuation (unshifted),
+    /// otherwise `space`, `enter`, `backspace`, `tab`, `esc`, `capslock`, `shift`, `rshift`,
+    /// `ctrl`, `alt`, `f1` to `f12`, `up`, `down`, `left`, `right`, `home`, `end`,
+    /// `insert`, `delete`, `pageup` or `pagedown`.
+    pub fn parse(name: &str) -> Option<Key> {
+        let name = name.to_ascii_lowercase();
+        KEYS.iter().position(|(key_name, _, _)| *key_name == name).map(Key)
+    }
+
+    pub fn name(self) -> &'static str {
+        KEYS[self.0].0
+    }
+
+    /// The keys to hold to type a character: the key itself, and whether shift is needed.
+    pub fn for_char(c: char) -> Option<(Key, bool)> {
+        match c {
+            ' ' => Key::parse("space").map(|key| (key, false)),
+            'A'..='Z' => Key::parse(&c.to_ascii_lowercase().to_string()).map(|key| (key, true)),
+            c => match SHIFTED_CHARS.iter().find(|(shifted, _)| *shifted == c) {
+                Some((_, name)) => Key::parse(name).map(|key| (key, true)),
+                None => Key::parse(&c.to_string()).map(|key| (key, false)),
+            },
+        }
+    }
+
+    /// The key for a Windows virtual-key code, as sent with the window key events.
+    /// Both shift keys have the same code, they are reported as the left one.
+    pub fn for_virtual_key(code: u32) -> Option<Key> {
+        let name = match code {
+            0x30..=0x39 | 0x41..=0x5a => return Key::parse(&(code as u8 as char).to_string()),
+            0x70..=0x7b => return Key::parse(&format!("f{}", code - 0x6f)),
+            0x08 => "backspace", 0x09 => "tab", 0x0d => "enter", 0x10 => "shift", 0x11 => "ctrl",
+            0x12 => "alt", 0x14 => "capslock", 0x1b => "esc", 0x20 => "space",
+            0x21 => "pageup", 0x22 => "pagedown", 0x23 => "end", 0x24 => "home",
+            0x25 => "left", 0x26 => "up", 0x27 => "right", 0x28 => "down", 0x2d => "insert", 0x2e => "delete",
+            // The punctuation keys, where they are on a US layout
+            0xba => ";", 0xbb => "=", 0xbc => ",", 0xbd => "-", 0xbe => ".", 0xbf => "/", 0xc0 => "`",
+            0xdb => "[", 0xdc => "\\", 0xdd => "]", 0xde => "'",
+            _ => return None,
+        };
+        Key::parse(name)
+    }
+
+    fn scan_code(self, released: bool) -> Vec<u8> {
+        let (_, code, extended) = KEYS[self.0];
+        let mut bytes = Vec::new();
+        if extended {
+            bytes.push(EXTENDED_PREFIX);
+        }
+        if released {
+            bytes.push(BREAK_PREFIX);
+        }
+        bytes.push(code);
+        bytes
+    }
+}
+
+/// A PS/2 keyboard sending scan code set 2, as the device generating the clock.
+///
+/// Both lines are open collector: the keyboard pulls them low or lets the pull-ups hold them high.
+/// Each byte is sent as a start bit (0), 8 data bits from the least significant one,
+/// an odd parity bit and a stop bit (1), the data changing while the clock is high
+/// so that the host can read it on the falling edge of the clock (e.g. on CA1).
+pub struct Ps2Keyboard {
+    to_send: VecDeque<u8>,
+    // Bits of the byte being sent, from the start bit, and how many went out
+    frame: u16,
+    frame_bit: u8,
+    sending: bool,
+    clock_low: bool,
+    data_low: bool,
+    next_change: usize,
+    half_period_cycles: usize,
+    byte_gap_cycles: usize,
+}
+
+impl Ps2Keyboard {
+    pub fn new(clock_freq: usize) -> Ps2Keyboard {
+        Ps2Keyboard {
+            to_send: VecDeque::new(),
+            frame: 0,
+            frame_bit: 0,
+            sending: false,
+            clock_low: false,
+            data_low: false,
+            next_change: 0,
+            half_period_cycles: us_to_cycles(CLOCK_PERIOD / 2, clock_freq).max(1),
+            byte_gap_cycles: us_to_cycles(BYTE_GAP, clock_freq).max(1),
+        }
+    }
+
+    pub fn key_down(&mut self, key: Key) {
+        self.to_send.extend(key.scan_code(false));
+    }
+
+    pub fn key_up(&mut self, key: Key) {
+        self.to_send.extend(key.scan_code(true));
+    }
+
+    /// Press and release the keys needed for every character of `text`,
+    /// the characters without a key being skipped.
+    pub fn type_text(&mut self, text: &str) {
+        let shift = Key::parse("shift").unwrap();
+        for (key, shifted) in text.chars().filter_map(Key::for_char) {
+            if shifted {
+                self.key_down(shift);
+            }
+            self.key_down(key);
+            self.key_up(key);
+            if shifted {
+                self.key_up(shift);
+            }
+        }
+    }
+
+    fn frame_level(&self) -> bool {
+        self.frame & (1 << self.frame_bit) != 0
+    }
+
+    pub fn clock(&mut self, cycle: usize) {
+        if cycle < self.next_change {
+            return;
+        }
+
+        if !self.sending {
+            if let Some(byte) = self.to_send.pop_front() {
+                let parity = (byte.count_ones() % 2 == 0) as u16;
+                self.frame = ((byte as u16) << 1) | (parity << 9) | (1 << 10);
+                self.frame_bit = 0;
+                self.sending = true;
+                self.data_low = !self.frame_level();
+                self.next_change = cycle + self.half_period_cycles;
+            }
+        } else if !self.clock_low {
+            // Falling edge, the host reads the data
+            self.clock_low = true;
+            self.next_change = cycle + self.half_period_cycles;
+        } else {
+            self.clock_low = false;
+            self.frame_bit += 1;
+            if self.frame_bit == 11 {
+                self.sending = false;
+                self.data_low = false;
+                self.next_change = cycle + self.byte_gap_cycles;
+            } else {
+                self.data_low = !self.frame_level();
+                self.next_change = cycle + self.half_period_cycles;
+            }
+        }
+    }
+
+    pub fn is_clock_low(&self) -> bool {
+        self.clock_low
+    }
+
+    pub fn is_data_low(&self) -> bool {
+        self.data_low
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Clock the keyboard like a host would, reading the data on the falling edges of the clock
+    fn receive_bytes(keyboard: &mut Ps2Keyboard, cycles: usize) -> Vec<u8> {
+        let mut bits = Vec::new();
+        let mut clock_was_low = false;
+        for cycle in 1..=cycles {
+            keyboard.clock(cycle);
+            if keyboard.is_clock_low() && !clock_was_low {
+                bits.push(!keyboard.is_data_low());
+            }
+            clock_was_low = keyboard.is_clock_low();
+        }
+        assert!(!keyboard.is_clock_low() && !keyboard.is_data_low(), "The lines should be released when idle");
+
+        bits.chunks(11).map(|frame| {
+            assert_eq!(11, frame.len());
+            assert!(!frame[0], "start bit");
+            assert!(frame[10], "stop bit");
+            assert_eq!(1, frame[1..10].iter().filter(|&&bit| bit).count() % 2, "odd parity");
+            frame[1..9].iter().rev().fold(0, |byte, &bit| (byte << 1) | bit as u8)
+        }).collect()
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(Some((Key::parse("a").unwrap(), true)), Key::for_char('A'));
+        assert_eq!(Some((Key::parse("1").unwrap(), true)), Key::for_char('!'));
+        assert_eq!(Some((Key::parse("space").unwrap(), false)), Key::for_char(' '));
+        assert_eq!(None, Key::for_char('é'));
+        assert_eq!(None, Key::parse("hyper"));
+        assert_eq!("pageup", Key::parse("PageUp").unwrap().name());
+        assert_eq!(Key::parse("a"), Key::for_virtual_key(0x41));
+        assert_eq!(Key::parse("7"), Key::for_virtual_key(0x37));
+        assert_eq!(Key::parse("f12"), Key::for_virtual_key(0x7b));
+        assert_eq!(Key::parse("\\"), Key::for_virtual_key(0xdc));
+        assert_eq!(None, Key::for_virtual_key(0x5b));
+    }
+
+    #[test]
+    fn scan_codes() {
+        let mut keyboard = Ps2Keyboard::new(1_000_000);
+        keyboard.key_down(Key::parse("a").unwrap());
+        keyboard.key_up(Key::parse("a").unwrap());
+        keyboard.key_down(Key::parse("up").unwrap());
+        keyboard.key_up(Key::parse("up").unwrap());
+
+        // 11 bits of 80µs and a 200µs gap per byte
+        assert_eq!(vec![0x1c, 0xf0, 0x1c, 0xe0, 0x75, 0xe0, 0xf0, 0x75], receive_bytes(&mut keyboard, 8 * 1_080 + 1));
+    }
+
+    #[test]
+    fn type_text() {
+        let mut keyboard = Ps2Keyboard::new(1_000_000);
+        keyboard.type_text("Hi!");
+
+        assert_eq!(vec![
+            0x12, 0x33, 0xf0, 0x33, 0xf0, 0x12,
+            0x43, 0xf0, 0x43,
+            0x12, 0x16, 0xf0, 0x16, 0xf0, 0x12,
+        ], receive_bytes(&mut keyboard, 15 * 1_080 + 1));
+    }
+}
@@ -1,37 +1,27 @@
-use std::thread::{self, JoinHandle};
-use std::sync::mpsc::{self, Sender, Receiver, TryRecvError};
+use std::sync::mpsc::Sender;
+use std::path::PathBuf;
+use std::fs;
 use crate::{ToGuiMessage, logger::LogMessage};
+use super::us_to_cycles;
 
-const FONT_TABLE: [char; 256] = [
-    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
-    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
-    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
-    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '¥', ']', '^', '_',
-    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
-    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '→', '←',
-    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-    ' ', '｡', '｢', '｣', '､', '･', 'ｦ', 'ｧ', 'ｨ', 'ｩ', 'ｪ', 'ｫ', 'ｬ', 'ｭ', 'ｮ', 'ｯ',
-    'ｰ', 'ｱ', 'ｲ', 'ｳ', 'ｴ', 'ｵ', 'ｶ', 'ｷ', 'ｸ', 'ｹ', 'ｺ', 'ｻ', 'ｼ', 'ｽ', 'ｾ', 'ｿ',
-    'ﾀ', 'ﾁ', 'ﾂ', 'ﾃ', 'ﾄ', 'ﾅ', 'ﾆ', 'ﾇ', 'ﾈ', 'ﾉ', 'ﾊ', 'ﾋ', 'ﾌ', 'ﾍ', 'ﾎ', 'ﾏ',
-    'ﾐ', 'ﾑ', 'ﾒ', 'ﾓ', 'ﾔ', 'ﾕ', 'ﾖ', 'ﾗ', 'ﾘ', 'ﾙ', 'ﾚ', 'ﾛ', 'ﾜ', 'ﾝ', 'ﾞ', 'ﾟ',
-    'α', 'ä', 'β', 'ε', 'μ', 'σ', 'ρ', 'g', '√', '¹', 'j', 'ˣ', '¢', 'Ⱡ', 'ñ', 'ö',
-    'p', 'q', 'θ', '∞', 'Ω', 'ü', 'Σ', 'π', '𝔵', 'y', '千', '万', '円', '÷', ' ', '█',
-];
-
-pub enum SysToLcdMessage {
-    RegisterPinChange(bool),
-    ReadWritePinChange(bool),
-    EnablePinChange(bool),
-    // u8 is the data bit position, with 0 <= u8 <= 7, and bool is the electric level
-    DataPinChange((u8, bool)),
-    AllowOneUpdate,
-    AllowAllUpdates,
-    Exit,
-}
+mod cgrom;
+mod frame;
+pub use cgrom::CharRom;
+use frame::{Frame, Pixel};
+
+// Size of the rendered screenshots, in picture pixels per LCD dot
+const SCREENSHOT_SCALE: usize = 4;
+// Execution times in µs, for the typical 270 kHz oscillator frequency
+const CLEAR_HOME_TIME: usize = 1520;
+const INSTRUCTION_TIME: usize = 37;
+// The cursor blinks every 102 400 periods of the 250 kHz oscillator
+const BLINK_TIME: usize = 409_600;
+
+// Blank dots around the characters, and between them
+const FRAME_MARGIN: usize = 3;
+const CHAR_GAP: usize = 1;
 
+#[derive(Debug)]
 pub struct LCDPins {
     pub rs: bool,
     pub rw: bool,
@@ -39,22 +29,77 @@ pub struct LCDPins {
     pub data: u8,
 }
 
+/// Levels driven by the LCD on its data pins while E is high during a read.
+/// Only the `driven` bits are outputs, the other data pins are in high impedance.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DataOutput {
+    pub data: u8,
+    pub driven: u8,
+}
+
+/// Number of characters of the LCD module, with the controller being the same for every size.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Geometry {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl Geometry {
+    /// Parse a size written as columns x rows, e.g. "20x4", only accepting the usual modules.
+    pub fn parse(size: &str) -> Option<Geometry> {
+        match size.to_ascii_lowercase().as_str() {
+            "16x1" => Some(Geometry { columns: 16, rows: 1 }),
+            "16x2" => Some(Geometry { columns: 16, rows: 2 }),
+            "20x2" => Some(Geometry { columns: 20, rows: 2 }),
+            "20x4" => Some(Geometry { columns: 20, rows: 4 }),
+            "40x2" => Some(Geometry { columns: 40, rows: 2 }),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry { columns: 16, rows: 2 }
+    }
+}
+
+/// Snapshot of what the LCD shows and of the state behind it, for tests and tools.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LcdState {
+    /// Text of each row of the module, see `LCD::line`
+    pub lines: Vec<String>,
+    pub ddram: [u8; 0x80],
+    /// DDRAM address the next character is written to
+    pub cursor_addr: u8,
+    /// Row and column of the cursor, if it is on the screen and the address counter points to the DDRAM
+    pub cursor_position: Option<(usize, usize)>,
+    pub display_on: bool,
+    pub cursor_on: bool,
+    pub blink_on: bool,
+    /// DDRAM address shown in the first column, moved by display shifts
+    pub display_shift: u8,
+}
+
 // enum ConfigBit { ValueIfHigh, ValueIfLow }
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 enum DataLength { Eigth, Four }
+#[derive(PartialEq, Debug)]
 enum NbLines { Two, One }
+#[derive(Debug)]
 enum Font { FiveByTen, FiveByEight }
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Debug)]
 enum DisplayState { On, Off }
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 enum CursorState { On, Off }
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 enum BlinkState { On, Off }
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 enum ShiftDir { Right, Left }
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Debug)]
 enum DisplayBehavior { Both, MoveCursor, ShiftDisplay }
 
+#[derive(Debug)]
 struct LCDConfig {
     data_length: DataLength,
     nb_lines: NbLines,
@@ -66,33 +111,68 @@ struct LCDConfig {
     display_behavior: DisplayBehavior,
 }
 
+#[derive(Debug)]
 enum AddrCounter {
     Ddram,
     Cgram
 }
 
 pub struct LCD {
+    geometry: Geometry,
+    char_rom: CharRom,
     pins: LCDPins,
     screen: String,
+    // Visible text when the screen was last logged, to only log it when it changes
+    logged_lines: Vec<String>,
     current_blink_state: BlinkState,
-    cycles_before_blink: usize,
+    // CPU cycle count at which the blinking cursor changes state
+    next_blink: usize,
     display_addr: u8,
     addr_counter: AddrCounter,
     ddram_data: [u8; 0x80],
     ddram_addr: u8,
+    // 8 user-defined 5x8 characters (or 4 5x10 ones), one byte per row with the leftmost pixel in bit 4
+    cgram_data: [u8; 0x40],
+    cgram_addr: u8,
     config: LCDConfig,
     waiting_for_lower_half: bool,
+    // RS and R/W of the first half of the current 4-bit transfer
+    first_half_rs_rw: (bool, bool),
+    // Consecutive instruction writes with 0x3 on DB4-DB7, up to 3, which put the interface
+    // back in 8-bit mode from any state (initialization by instruction)
+    init_nibbles: u8,
+    // Data pins wired to the system, the others being held high by the internal pull-ups
+    connected_data_pins: u8,
+    // Busy flag and address counter, or RAM data, being read by the MPU
+    read_data: u8,
+    data_output: Option<DataOutput>,
+    // Current CPU cycle count, and that of the end of the instruction being executed
+    cycle: usize,
+    busy_until: usize,
+    clock_freq: usize,
+    // Operations ignored because they were sent while the LCD was busy
+    dropped_count: usize,
+    // Whether every operation is sent to the LCD log, only when that log is written to a file
+    operation_log_enabled: bool,
     gui_update_allowed: Option<bool>,
+    // Where to save a picture of the last frame when the LCD is stopped
+    screenshot_path: Option<PathBuf>,
     tx_log_msgs: Sender<LogMessage>,
     tx_to_gui: Sender<ToGuiMessage>,
 }
 
 impl LCD {
     pub fn new(
+        geometry: Geometry,
+        char_rom: CharRom,
+        clock_freq: usize,
+        screenshot_path: Option<PathBuf>,
         tx_log_msgs: Sender<LogMessage>,
         tx_to_gui: Sender<ToGuiMessage>
     ) -> LCD {
         let mut lcd = LCD {
+            geometry,
+            char_rom,
             pins: LCDPins {
                 rs: false,
                 rw: false,
@@ -100,12 +180,16 @@ impl LCD {
                 data: 0b0000_0000,
             },
             screen: String::new(),
+            logged_lines: Vec::new(),
             current_blink_state: BlinkState::Off,
-            cycles_before_blink: 102_400,
+            next_blink: 0,
             display_addr: 0x0,
             addr_counter: AddrCounter::Ddram,
-            ddram_data: [0xff; 0x80],
+            // The internal reset circuit clears the display when the LCD is powered on
+            ddram_data: [0x20; 0x80],
             ddram_addr: 0x0,
+            cgram_data: [0xff; 0x40],
+            cgram_addr: 0x0,
             config: LCDConfig {
                 data_length: DataLength::Eigth,
                 nb_lines: NbLines::One,
@@ -117,7 +201,18 @@ impl LCD {
                 display_behavior: DisplayBehavior::MoveCursor,
             },
             waiting_for_lower_half: false,
+            first_half_rs_rw: (false, false),
+            init_nibbles: 0,
+            connected_data_pins: 0xff,
+            read_data: 0x00,
+            data_output: None,
+            cycle: 0,
+            busy_until: 0,
+            clock_freq,
+            dropped_count: 0,
+            operation_log_enabled: false,
             gui_update_allowed: None,
+            screenshot_path,
             tx_log_msgs,
             tx_to_gui,
         };
@@ -126,142 +221,401 @@ impl LCD {
         lcd
     }
 
-    pub fn run(mut self, rx_sys_msgs: Receiver<SysToLcdMessage>) -> JoinHandle<()> {
-        thread::Builder::new().name("LCD thread".to_string()).spawn(move || {
-            let (tx_timer_msgs, rx_timer_msgs) = mpsc::channel();
-            let timer = timer::MessageTimer::new(tx_timer_msgs);
-
-            let _guard = timer.schedule_repeating(chrono::Duration::microseconds(4), ());
-
-            'lcd_thread_main: loop {
-                rx_timer_msgs.recv().expect("Error receiving LCD clock pulse");
-                
-                // Blinking mecanism
-                if self.config.blink_state == BlinkState::On {
-                    if self.cycles_before_blink == 0 {
-                        self.cycles_before_blink = 102_400;
-                        self.current_blink_state = match self.current_blink_state {
-                            BlinkState::On => BlinkState::Off,
-                            BlinkState::Off => BlinkState::On,
-                        };
-                        self.update_screen();
-                    } else {
-                        self.cycles_before_blink -= 1;
-                    }
-                }
+    /// Advance the LCD to that CPU cycle count, called on every cycle of the system clock.
+    pub fn clock(&mut self, cycle: usize) {
+        self.cycle = cycle;
+
+        // Blinking mecanism
+        if self.config.blink_state == BlinkState::On && self.cycle >= self.next_blink {
+            self.next_blink = self.cycle + us_to_cycles(BLINK_TIME, self.clock_freq);
+            self.current_blink_state = match self.current_blink_state {
+                BlinkState::On => BlinkState::Off,
+                BlinkState::Off => BlinkState::On,
+            };
+            self.update_screen();
+        }
+    }
+
+    /// Tell which data pins are wired, as a mask with bit n set for DBn.
+    pub fn set_connected_data_pins(&mut self, mask: u8) {
+        self.connected_data_pins = mask;
+    }
+
+    /// Send every operation received to the LCD log, see `LogMessage::LcdOperation`.
+    pub fn enable_operation_log(&mut self) {
+        self.operation_log_enabled = true;
+    }
+
+    pub fn register_pin_change(&mut self, level: bool) {
+        self.pins.rs = level;
+    }
+
+    pub fn read_write_pin_change(&mut self, level: bool) {
+        self.pins.rw = level;
+    }
+
+    /// Levels the LCD drives on its data pins, if any.
+    pub fn data_output(&self) -> Option<DataOutput> {
+        self.data_output
+    }
+
+    /// Send the next frame to the GUI, but not the following ones.
+    pub fn allow_one_update(&mut self) {
+        self.gui_update_allowed = Some(true);
+    }
 
-                'read_sys_msgs: loop { match rx_sys_msgs.try_recv() {
-                    Err(TryRecvError::Disconnected) => panic!("SYS thread has hung up"),
-                    Err(TryRecvError::Empty) => break 'read_sys_msgs,
-                    Ok(SysToLcdMessage::DataPinChange((bit, level))) => {
-                        self.pins.data = match (self.waiting_for_lower_half, level) {
-                            (false, true) => self.pins.data | (1 << bit),
-                            (false, false) => self.pins.data & !(1 << bit),
-                            (true, true) => self.pins.data | (1 << (bit - 4)),
-                            (true, false) => self.pins.data & !(1 << (bit - 4)),
-                        };
+    pub fn allow_all_updates(&mut self) {
+        self.gui_update_allowed = None;
+
+        self.send_screen_to_gui();
+    }
+
+    /// Show the last frame, and save it as a screenshot if asked to.
+    pub fn stop(&self) {
+        if self.waiting_for_lower_half {
+            log!(self.tx_log_msgs, "\nLCD WARNING: stopped halfway through a 4-bit transfer, \
+                the next operation would be out of sync");
+        }
+        self.send_screen_to_gui();
+        self.save_screenshot();
+    }
+
+    pub fn data_pin_change(&mut self, bit: u8, level: bool) {
+        // DB0-DB3 aren't used for the lower half of a 4-bit transfer, which is read on DB4-DB7
+        if self.waiting_for_lower_half && bit < 4 {
+            return;
+        }
+        self.pins.data = match (self.waiting_for_lower_half, level) {
+            (false, true) => self.pins.data | (1 << bit),
+            (false, false) => self.pins.data & !(1 << bit),
+            (true, true) => self.pins.data | (1 << (bit - 4)),
+            (true, false) => self.pins.data & !(1 << (bit - 4)),
+        };
+    }
+
+    pub fn enable_pin_change(&mut self, level: bool) {
+        match (self.pins.e, level) {
+            (false, true) => {
+                self.pins.e = level;
+                // Level of DB4-DB7, where each half of a 4-bit transfer is sent
+                let nibble = if self.waiting_for_lower_half { self.pins.data & 0x0f } else { self.pins.data >> 4 };
+
+                match (&self.config.data_length, self.waiting_for_lower_half) {
+                    (DataLength::Four, false) => {
+                        self.waiting_for_lower_half = true;
+                        self.first_half_rs_rw = (self.pins.rs, self.pins.rw);
+                        // Reads are done on the first half, which sends the upper 4 bits on DB4-DB7
+                        if self.pins.rw {
+                            self.read_pins();
+                            self.data_output = Some(DataOutput { data: self.read_data & 0xf0, driven: 0xf0 });
+                        }
                     },
-                    Ok(SysToLcdMessage::RegisterPinChange(level)) => self.pins.rs = level,
-                    Ok(SysToLcdMessage::ReadWritePinChange(level)) => self.pins.rw = level,
-                    Ok(SysToLcdMessage::EnablePinChange(level)) => {
-                        match (self.pins.e, level) {
-                            (false, true) => {
-                                self.pins.e = level;
-
-                                match (&self.config.data_length, self.waiting_for_lower_half) {
-                                    (DataLength::Four, false) => self.waiting_for_lower_half = true,
-                                    (DataLength::Four, true) => {
-                                        self.waiting_for_lower_half = false;
-                                        self.read_pins();
-                                    },
-                                    (DataLength::Eigth, _) => self.read_pins(),
-                                }
-                            },
-                            (true, false) => self.pins.e = level,
-                            (_, _) => {},
+                    (DataLength::Four, true) => {
+                        self.waiting_for_lower_half = false;
+                        if self.first_half_rs_rw != (self.pins.rs, self.pins.rw) {
+                            log!(self.tx_log_msgs, "\nLCD WARNING: the halves of a 4-bit transfer have different \
+                                RS/RW ({}/{} then {}/{}), the program and the LCD are out of sync", 
+                                self.first_half_rs_rw.0 as u8, self.first_half_rs_rw.1 as u8, 
+                                self.pins.rs as u8, self.pins.rw as u8);
+                        }
+                        if self.pins.rw {
+                            self.data_output = Some(DataOutput { data: self.read_data << 4, driven: 0xf0 });
+                        } else {
+                            self.read_pins();
                         }
-                    }
-                    Ok(SysToLcdMessage::AllowOneUpdate) => self.gui_update_allowed = Some(true),
-                    Ok(SysToLcdMessage::AllowAllUpdates) => {
-                        self.gui_update_allowed = None;
-
-                        self.send_screen_to_gui();
                     },
-                    Ok(SysToLcdMessage::Exit) => {
-                        self.send_screen_to_gui();
-                        
-                        break 'lcd_thread_main
+                    (DataLength::Eigth, _) => {
+                        self.pins.data |= !self.connected_data_pins;
+                        // With only DB4-DB7 wired, the function sets of the initialization are the only
+                        // operations meant to be received in 8-bit mode
+                        let init_function_set = !self.pins.rs && !self.pins.rw && (nibble == 0x2 || nibble == 0x3);
+                        if self.connected_data_pins & 0x0f == 0 && !init_function_set {
+                            log!(self.tx_log_msgs, "\nLCD WARNING: {} received in 8-bit mode with only DB4-DB7 \
+                                connected, the program and the LCD are out of sync", self.operation_name());
+                        }
+                        self.read_pins();
+                        if self.pins.rw {
+                            self.data_output = Some(DataOutput { data: self.read_data, driven: 0xff });
+                        }
                     },
-                }};
+                }
+
+                self.init_nibbles = if !self.pins.rs && !self.pins.rw && nibble == 0x3 {
+                    (self.init_nibbles + 1).min(3)
+                } else {
+                    0
+                };
+            },
+            (true, false) => {
+                self.pins.e = level;
+                // The data pins go back to high impedance
+                self.data_output = None;
+            },
+            (_, _) => {},
+        }
+    }
+
+    pub fn dump_state(&self) -> String {
+        let mut dump = format!("{:#?}\n{:#?}\nAddress counter: {:?}\nDDRAM address: {:#04x}\n\
+            Display address: {:#04x}\nWaiting for lower half: {}\nInitialization 0x3 nibbles: {}\nData output: {:?}\n\
+            Busy until cycle: {} (now at {})\nDropped operations: {}\n{}\n",
+            self.pins, self.config, self.addr_counter, self.ddram_addr,
+            self.display_addr, self.waiting_for_lower_half, self.init_nibbles, self.data_output, 
+            self.busy_until, self.cycle, self.dropped_count, self.screen);
+        dump.push_str(&self.render().to_half_blocks());
+
+        let state = self.state();
+        dump.push_str(&format!("Cursor position: {:?}\nText:\n", state.cursor_position));
+        for line in &state.lines {
+            dump.push_str(&format!("  \"{}\"\n", line));
+        }
+
+        dump.push_str("DDRAM:");
+        for (addr, char_code) in self.ddram_data.iter().enumerate() {
+            if addr % 16 == 0 {
+                dump.push_str(&format!("\n{:02x}:", addr));
+            }
+            dump.push_str(&format!(" {:02x}", char_code));
+        }
+
+        dump.push_str(&format!("\nCGRAM address: {:#04x}\nCGRAM:", self.cgram_addr));
+        for (addr, pixels) in self.cgram_data.iter().enumerate() {
+            if addr % 8 == 0 {
+                dump.push_str(&format!("\n{:02x}:", addr));
+            }
+            dump.push_str(&format!(" {:02x}", pixels));
+        }
+
+        dump
+    }
+
+    /// Text shown on that row of the module, without the cursor nor trailing spaces,
+    /// the user-defined characters being approximated with braille patterns.
+    /// Rows are empty while the display is off.
+    pub fn line(&self, row: usize) -> String {
+        if self.config.display_state == DisplayState::Off {
+            return String::new();
+        }
+
+        let line: String = (0..self.geometry.columns).map(|column| match self.displayed_addr(row, column) {
+            Some(addr) if self.ddram_data[addr as usize] < 0x10 => self.cgram_char(self.ddram_data[addr as usize]),
+            Some(addr) => self.char_rom.char(self.ddram_data[addr as usize]),
+            None => ' ',
+        }).collect();
+        line.trim_end_matches(' ').to_string()
+    }
+
+    pub fn state(&self) -> LcdState {
+        let cursor_position = match self.addr_counter {
+            AddrCounter::Ddram => (0..self.geometry.rows)
+                .flat_map(|row| (0..self.geometry.columns).map(move |column| (row, column)))
+                .find(|&(row, column)| self.displayed_addr(row, column) == Some(self.ddram_addr)),
+            AddrCounter::Cgram => None,
+        };
+
+        LcdState {
+            lines: (0..self.geometry.rows).map(|row| self.line(row)).collect(),
+            ddram: self.ddram_data,
+            cursor_addr: self.ddram_addr,
+            cursor_position,
+            display_on: self.config.display_state == DisplayState::On,
+            cursor_on: self.config.cursor_state == CursorState::On,
+            blink_on: self.config.blink_state == BlinkState::On,
+            display_shift: self.display_addr,
+        }
+    }
+
+    fn push_ddram_char(&self, string: &mut String, addr: u8) {
+        let char_code = self.ddram_data[addr as usize];
+
+        if self.config.cursor_state == CursorState::On && addr == self.ddram_addr {
+            string.push_str("\u{200c}\u{0332}");
+        }
+        
+        if self.current_blink_state == BlinkState::On && addr == self.ddram_addr {
+            // All the dots are on
+            string.push('█');
+        } else if char_code < 0x10 {
+            string.push(self.cgram_char(char_code));
+        } else {
+            string.push(self.char_rom.char(char_code));
+        }
+    }
+
+    // The 5x10 font can only be used with a single line, the F bit is ignored in 2-line mode
+    fn uses_5x10_font(&self) -> bool {
+        match (&self.config.nb_lines, &self.config.font) {
+            (NbLines::One, Font::FiveByTen) => true,
+            (_, _) => false,
+        }
+    }
+
+    // Rows of the user-defined character displayed for a character code between 0x00 and 0x0f
+    fn cgram_glyph(&self, char_code: u8) -> &[u8] {
+        if self.uses_5x10_font() {
+            // Character code bit 0 is ignored, and each character takes 16 bytes, of which 11 are displayed
+            let start = (char_code as usize & 0b0110) * 8;
+            &self.cgram_data[start..start + 11]
+        } else {
+            // Codes 0x08-0x0f are the same characters as 0x00-0x07
+            let start = (char_code as usize & 0b0111) * 8;
+            &self.cgram_data[start..start + 8]
+        }
+    }
+
+    // Dot rows of a character, the last one being the cursor line:
+    // 8 rows with the 5x8 font, 11 with the 5x10 one
+    fn glyph(&self, char_code: u8) -> Vec<u8> {
+        if char_code < 0x10 {
+            return self.cgram_glyph(char_code).to_vec();
+        }
+
+        let mut rows = self.char_rom.glyph(char_code).to_vec();
+        if self.uses_5x10_font() {
+            rows.extend_from_slice(&self.char_rom.descender(char_code));
+            rows.push(0x00);
+        }
+        rows
+    }
+
+    // DDRAM address of the character shown on that row and column of the module, if any.
+    // Modules with more than 2 rows continue each DDRAM line on the rows below,
+    // hence the 00/40/14/54 row addresses of a 20x4 module.
+    fn displayed_addr(&self, row: usize, column: usize) -> Option<u8> {
+        let offset = self.display_addr as usize + (row / 2) * self.geometry.columns + column;
+        match (&self.config.nb_lines, row % 2) {
+            (NbLines::One, 0) => Some((offset % 0x50) as u8),
+            // The commons of the second line aren't driven in 1-line mode
+            (NbLines::One, _) => None,
+            (NbLines::Two, line) => Some((line * 0x40 + offset % 0x28) as u8),
+        }
+    }
+
+    /// Dot by dot picture of the screen, as seen on the physical display.
+    fn render(&self) -> Frame {
+        let Geometry { columns, rows: nb_rows } = self.geometry;
+        let char_height = if self.uses_5x10_font() { 11 } else { 8 };
+        let mut frame = Frame::new(
+            2 * FRAME_MARGIN + columns * (5 + CHAR_GAP) - CHAR_GAP,
+            2 * FRAME_MARGIN + nb_rows * (char_height + CHAR_GAP) - CHAR_GAP,
+        );
+
+        for row in 0..nb_rows {
+            for column in 0..columns {
+                let addr = self.displayed_addr(row, column);
+                let mut rows = match addr {
+                    Some(addr) => self.glyph(self.ddram_data[addr as usize]),
+                    None => vec![0x00; char_height],
+                };
+
+                if self.config.display_state == DisplayState::Off {
+                    rows = vec![0x00; char_height];
+                } else if addr == Some(self.ddram_addr) {
+                    // The blinking cursor lights the whole character, the underline cursor its last row
+                    if self.current_blink_state == BlinkState::On {
+                        rows = vec![0x1f; char_height];
+                    } else if self.config.cursor_state == CursorState::On {
+                        rows[char_height - 1] = 0x1f;
+                    }
+                }
+
+                let x0 = FRAME_MARGIN + column * (5 + CHAR_GAP);
+                let y0 = FRAME_MARGIN + row * (char_height + CHAR_GAP);
+                for (y, pixels) in rows.iter().enumerate() {
+                    for x in 0..5 {
+                        let pixel = if pixels & (0b1_0000 >> x) != 0 { Pixel::On } else { Pixel::Off };
+                        frame.set(x0 + x, y0 + y, pixel);
+                    }
+                }
+            }
+        }
+
+        frame
+    }
+
+    fn save_screenshot(&self) {
+        if let Some(path) = &self.screenshot_path {
+            let frame = self.render();
+            let data = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("png") => frame.to_png(SCREENSHOT_SCALE),
+                Some("ppm") => frame.to_ppm(SCREENSHOT_SCALE),
+                _ => frame.to_half_blocks().into_bytes(),
+            };
+            match fs::write(path, data) {
+                Ok(()) => println!("LCD screenshot written to {}", path.display()),
+                Err(err) => println!("Failed to write LCD screenshot to {}: {}", path.display(), err),
             }
-        }).unwrap()
+        }
     }
 
-    fn ddram_to_string(&self, start_addr: u8, end_addr: u8) -> String {
-        let mut string = String::new();
+    // A user-defined character can't be printed as text, so it is shrunk to a braille pattern,
+    // each dot being lit when any pixel of its 3x2 (or 2x2 on the right) block is lit
+    fn cgram_char(&self, char_code: u8) -> char {
+        let glyph = self.cgram_glyph(char_code);
+        // Braille dot bits, from the top row to the bottom one, for the left and right columns
+        const DOTS: [(u32, u32); 4] = [(0x01, 0x08), (0x02, 0x10), (0x04, 0x20), (0x40, 0x80)];
 
-        for (i, char_code) in self.ddram_data[(start_addr as usize)..(end_addr as usize)]
-        .iter().enumerate() {
-            if self.config.cursor_state == CursorState::On 
-            && start_addr + i as u8 == self.ddram_addr {
-                string.push_str("\u{200c}\u{0332}");
+        let mut pattern = 0;
+        for (row, &pixels) in glyph.iter().take(8).enumerate() {
+            let (left_dot, right_dot) = DOTS[row / 2];
+            if pixels & 0b1_1100 != 0 {
+                pattern |= left_dot;
             }
-            
-            if self.current_blink_state == BlinkState::On 
-            && start_addr + i as u8 == self.ddram_addr {
-                string.push(FONT_TABLE[0xff]);
-            } else {
-                string.push(FONT_TABLE[*char_code as usize]);
+            if pixels & 0b0_0011 != 0 {
+                pattern |= right_dot;
             }
         }
 
-        string
+        std::char::from_u32(0x2800 + pattern).unwrap()
+    }
+
+    // After each CGRAM access, the address counter moves as set by the entry mode, but the display never shifts
+    fn move_cgram_addr(&mut self) {
+        self.cgram_addr = match self.config.shift_dir {
+            ShiftDir::Left => self.cgram_addr.wrapping_sub(1),
+            ShiftDir::Right => self.cgram_addr + 1,
+        } & 0b0011_1111;
     }
 
     fn update_screen(&mut self) {
-        let addr = self.display_addr;
-        let mut new_screen = String::from("╔════════════════╗\n║");
-
-        match self.config.nb_lines {
-            NbLines::One => {
-                if addr > 0x30 {
-                    new_screen.push_str(&self.ddram_to_string(addr, 0x50));
-                    new_screen.push_str(&self.ddram_to_string(0x00, addr-0x30));
-                    new_screen.push_str("║\n║                ");
-                } else {
-                    new_screen.push_str(&self.ddram_to_string(addr, addr+0x10));
-                    new_screen.push_str("║\n║                ");
-                }
-            },
-            NbLines::Two => {
-                if addr > 0x18 {
-                    new_screen.push_str(&self.ddram_to_string(addr, 0x28));
-                    new_screen.push_str(&self.ddram_to_string(0x00, addr-0x18));
-                    new_screen.push_str("║\n║");
-                    new_screen.push_str(&self.ddram_to_string(addr+0x40, 0x68));
-                    new_screen.push_str(&self.ddram_to_string(0x40, addr+0x28));
-                } else {
-                    new_screen.push_str(&self.ddram_to_string(addr, addr+0x10));
-                    new_screen.push_str("║\n║");
-                    new_screen.push_str(&self.ddram_to_string(addr+0x40, addr+0x50));
+        let border = "═".repeat(self.geometry.columns);
+        let mut new_screen = format!("╔{}╗\n", border);
+
+        for row in 0..self.geometry.rows {
+            new_screen.push('║');
+            for column in 0..self.geometry.columns {
+                match self.displayed_addr(row, column) {
+                    Some(addr) => self.push_ddram_char(&mut new_screen, addr),
+                    None => new_screen.push(' '),
                 }
-            },
+            }
+            new_screen.push_str("║\n");
         }
 
-        new_screen.push_str("║\n╚════════════════╝");
+        new_screen.push_str(&format!("╚{}╝", border));
     
         self.screen = new_screen;
 
-        if self.config.display_state == DisplayState::On {
-            log!(self.tx_log_msgs, "\n{}", self.screen);
-        
-            
-            if self.gui_update_allowed != Some(false) {
-                if self.gui_update_allowed == Some(true) {
-                    self.gui_update_allowed = Some(false);
+        // The cursor and the blinking aren't worth a new frame in the log, the text is
+        let lines = self.state().lines;
+        if lines != self.logged_lines {
+            for (row, line) in lines.iter().enumerate() {
+                let logged_line = self.logged_lines.get(row).map_or("", String::as_str);
+                if line != logged_line {
+                    log!(self.tx_log_msgs, "\nLCD row {}: \"{}\" -> \"{}\"", row, logged_line, line);
                 }
-                self.send_screen_to_gui();
             }
+            if self.config.display_state == DisplayState::On {
+                log!(self.tx_log_msgs, "\n{}", self.screen);
+            }
+            self.logged_lines = lines;
+        }
+
+        if self.config.display_state == DisplayState::On && self.gui_update_allowed != Some(false) {
+            if self.gui_update_allowed == Some(true) {
+                self.gui_update_allowed = Some(false);
+            }
+            self.send_screen_to_gui();
         }
     }
 
@@ -306,12 +660,69 @@ impl LCD {
         self.update_screen();
     }
 
+    fn is_busy(&self) -> bool {
+        self.cycle < self.busy_until
+    }
+
+    // Time taken by the operation on the pins, in CPU cycles
+    fn execution_time(&self) -> usize {
+        let time = match (self.pins.rs, self.pins.rw, self.pins.data) {
+            // Clear display and return home
+            (false, false, 0b0000_0001..=0b0000_0011) => CLEAR_HOME_TIME,
+            (_, _, _) => INSTRUCTION_TIME,
+        };
+        us_to_cycles(time, self.clock_freq)
+    }
+
+    fn operation_name(&self) -> String {
+        match (self.pins.rs, self.pins.rw) {
+            (false, false) => format!("instruction {:#010b} ({})", self.pins.data, Instruction::decode(self.pins.data).name()),
+            (false, true) => String::from("busy flag read"),
+            (true, false) => format!("data write {:#04x}", self.pins.data),
+            (true, true) => String::from("data read"),
+        }
+    }
+
+    // Instructions and RAM writes, the reads being left out since programs poll the busy flag all the time
+    fn log_operation(&self) {
+        match (self.pins.rs, self.pins.rw, &self.addr_counter) {
+            (false, false, _) => log!(self.tx_log_msgs, "\nLCD: {}", Instruction::decode(self.pins.data).name()),
+            (true, false, AddrCounter::Ddram) => log!(self.tx_log_msgs, "\nLCD: DDRAM[{:#04x}] <- {:#04x} '{}'", 
+                self.ddram_addr, self.pins.data, self.char_rom.char(self.pins.data)),
+            (true, false, AddrCounter::Cgram) => log!(self.tx_log_msgs, "\nLCD: CGRAM[{:#04x}] <- {:#07b}", 
+                self.cgram_addr, self.pins.data & 0b1_1111),
+            (_, true, _) => {},
+        }
+    }
+
     fn read_pins(&mut self) {
+        // The busy flag is the only thing that can be read while an instruction is executed,
+        // anything else sent during that time is ignored by the controller
+        let reading_busy_flag = !self.pins.rs && self.pins.rw;
+        if self.is_busy() && !reading_busy_flag {
+            self.dropped_count += 1;
+            if self.operation_log_enabled {
+                self.send_lcd_log(format!("Dropped, sent while busy: {}", self.operation_name()));
+            }
+            log!(self.tx_log_msgs, "\nLCD WARNING: {} sent while busy, dropped ({} cycles too early)", 
+                self.operation_name(), self.busy_until - self.cycle);
+            return;
+        }
+        if !reading_busy_flag {
+            self.busy_until = self.cycle + self.execution_time();
+        }
+        self.log_operation();
+        // Address of the RAM access, before the address counter moves
+        let ram_addr = match self.addr_counter {
+            AddrCounter::Ddram => self.ddram_addr,
+            AddrCounter::Cgram => self.cgram_addr,
+        };
+
         match (self.pins.rs, self.pins.rw) {
             // Instruction register write
             (false, false) => match self.pins.data.leading_zeros() {
-                // Not in the datasheet (to test on real hardware?)
-                8 => panic!("Unknown behavior for instruction 0b0000_0000!"),
+                // Not in the datasheet, usually the result of 4-bit transfers out of sync
+                8 => log!(self.tx_log_msgs, "\nLCD WARNING: instruction 0x00 isn't defined in the datasheet, dropped"),
                 // Clear display
                 7 => {
                     self.ddram_data = [0x20; 0x80];
@@ -357,7 +768,7 @@ impl LCD {
                         self.current_blink_state = BlinkState::Off;
                         BlinkState::Off
                     } else {
-                        self.cycles_before_blink = 102_400;
+                        self.next_blink = self.cycle + us_to_cycles(BLINK_TIME, self.clock_freq);
                         BlinkState::On
                     };
                     self.update_screen();
@@ -378,59 +789,68 @@ impl LCD {
                 },
                 // Function set
                 2 => {
-                    let mut fully_valid_data = true;
                     self.config.data_length = if self.pins.data & 0b0001_0000 == 0 {
-                        self.waiting_for_lower_half = false;
-                        if self.config.data_length == DataLength::Eigth {
-                            fully_valid_data = false;
+                        // From 8-bit mode, the next E pulse is the upper half of a transfer, which is only
+                        // certain after the 0x3 nibbles: they leave the interface in 8-bit mode whether
+                        // it was in 8-bit mode or in 4-bit mode, and in that case in sync or not
+                        if self.config.data_length == DataLength::Eigth && self.init_nibbles < 3 {
+                            log!(self.tx_log_msgs, "\nLCD WARNING: 4-bit mode set without the 0x3, 0x3, 0x3 \
+                                initialization sequence, this only works right after the LCD is powered on");
                         }
                         DataLength::Four
                     } else {
                         DataLength::Eigth
                     };
-                    if fully_valid_data {
-                        self.config.nb_lines = if self.pins.data & 0b0000_1000 == 0 {
-                            NbLines::One
-                        } else {
-                            NbLines::Two
-                        };
-                        self.config.font = if self.pins.data & 0b0000_0100 == 0 {
-                            Font::FiveByEight
-                        } else {
-                            Font::FiveByTen
-                        };
-                        self.ddram_data = [0x20; 0x80];
+                    // N and F are set even when switching to 4-bit mode, 
+                    // from DB3 and DB2 which are usually left unconnected (so high)
+                    self.config.nb_lines = if self.pins.data & 0b0000_1000 == 0 {
+                        NbLines::One
+                    } else {
+                        NbLines::Two
+                    };
+                    self.config.font = if self.pins.data & 0b0000_0100 == 0 {
+                        Font::FiveByEight
+                    } else {
+                        Font::FiveByTen
+                    };
+                    // DDRAM is kept, so a function set sent later to change N or F doesn't erase the text
 
-                        let display_state = self.config.display_state.clone();
-                        self.config.display_state = DisplayState::On;
-                        self.update_screen();
-                        self.config.display_state = display_state;
-                    }
+                    let display_state = self.config.display_state.clone();
+                    self.config.display_state = DisplayState::On;
+                    self.update_screen();
+                    self.config.display_state = display_state;
                 },
                 // Set CGRAM address
                 1 => {
                     self.addr_counter = AddrCounter::Cgram;
-                    todo!();
-                    // self.cgram_addr = self.pins.data & 0b0011_1111;
+                    self.cgram_addr = self.pins.data & 0b0011_1111;
                 },
                 // Set DDRAM address
                 0 => {
-                    self.addr_counter = AddrCounter::Ddram;
-                    self.ddram_addr = self.pins.data & 0b0111_1111;
-                    match self.config.nb_lines {
-                        NbLines::One => if let 0x50..=0xff = self.ddram_addr {
-                            panic!("Illegal DDRAM address: {}", self.ddram_addr);
-                        },
-                        NbLines::Two => if let 0x28..=0x3f | 0x68..=0xff = self.ddram_addr {
-                            panic!("Illegal DDRAM address: {}", self.ddram_addr);
-                        },
+                    let ddram_addr = self.pins.data & 0b0111_1111;
+                    let (legal, nb_lines) = match self.config.nb_lines {
+                        NbLines::One => (ddram_addr < 0x50, 1),
+                        NbLines::Two => (!matches!(ddram_addr, 0x28..=0x3f | 0x68..=0x7f), 2),
                     };
-                    self.update_screen();
+                    if legal {
+                        self.addr_counter = AddrCounter::Ddram;
+                        self.ddram_addr = ddram_addr;
+                        self.update_screen();
+                    } else {
+                        log!(self.tx_log_msgs, "\nLCD WARNING: DDRAM address {:#04x} doesn't exist in {}-line mode, dropped", 
+                            ddram_addr, nb_lines);
+                    }
                 },
                 _ => unreachable!(),
             },
             // Read busy flag (DB7) and address counter (DB0-DB6)
-            (false, true) => todo!(),
+            (false, true) => {
+                let addr = match self.addr_counter {
+                    AddrCounter::Ddram => self.ddram_addr,
+                    AddrCounter::Cgram => self.cgram_addr,
+                };
+                self.read_data = ((self.is_busy() as u8) << 7) | addr;
+            },
             // Write to DDRAM or CGRAM
             (true, false) => {
                 match &self.addr_counter {
@@ -439,11 +859,570 @@ impl LCD {
                         self.cursor_display_shift(self.config.shift_dir.clone(),
                             self.config.display_behavior.clone());
                     },
-                    &AddrCounter::Cgram => todo!(),
+                    &AddrCounter::Cgram => {
+                        self.cgram_data[self.cgram_addr as usize] = self.pins.data;
+                        self.move_cgram_addr();
+                        // Characters using that glyph change on the screen
+                        self.update_screen();
+                    },
                 }
             },
             // Read DDRAM or CGRAM
-            (true, true) => todo!(),
+            (true, true) => {
+                match self.addr_counter {
+                    AddrCounter::Ddram => {
+                        self.read_data = self.ddram_data[self.ddram_addr as usize];
+                        // The address counter moves as set by the entry mode, but the display never shifts on reads
+                        self.cursor_display_shift(self.config.shift_dir.clone(), DisplayBehavior::MoveCursor);
+                    },
+                    AddrCounter::Cgram => {
+                        self.read_data = self.cgram_data[self.cgram_addr as usize];
+                        self.move_cgram_addr();
+                    },
+                }
+            },
+        }
+
+        if self.operation_log_enabled {
+            let operation = match (self.pins.rs, self.pins.rw, &self.addr_counter) {
+                (false, false, _) => Instruction::decode(self.pins.data).description(),
+                (false, true, _) => format!("Read busy flag = {}, address counter = {:#04x}", 
+                    self.read_data >> 7, self.read_data & 0b0111_1111),
+                (true, rw, AddrCounter::Ddram) => {
+                    let char_code = if rw { self.read_data } else { self.pins.data };
+                    format!("{} DDRAM[{:#04x}] = '{}' ({:#04x})", if rw { "Read" } else { "Write" },
+                        ram_addr, self.char_rom.char(char_code), char_code)
+                },
+                (true, rw, AddrCounter::Cgram) => format!("{} CGRAM[{:#04x}] = {:#07b}", if rw { "Read" } else { "Write" },
+                    ram_addr, if rw { self.read_data } else { self.pins.data } & 0b1_1111),
+            };
+            self.send_lcd_log(operation);
+        }
+    }
+
+    fn send_lcd_log(&self, operation: String) {
+        self.tx_log_msgs.send(LogMessage::LcdOperation(format!("{:>10}: {}", self.cycle, operation)))
+            .expect("Logger thread has hung up");
+    }
+}
+
+// HD44780 instruction, with the value of each of its fields
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Instruction {
+    Unknown,
+    ClearDisplay,
+    ReturnHome,
+    EntryModeSet { increment: bool, shift: bool },
+    DisplayControl { display: bool, cursor: bool, blink: bool },
+    Shift { display: bool, right: bool },
+    FunctionSet { eight_bit: bool, two_lines: bool, five_by_ten: bool },
+    SetCgramAddr(u8),
+    SetDdramAddr(u8),
+}
+
+impl Instruction {
+    fn decode(instruction: u8) -> Instruction {
+        let flag = |n: u8| (instruction >> n) & 1 == 1;
+        match instruction.leading_zeros() {
+            8 => Instruction::Unknown,
+            7 => Instruction::ClearDisplay,
+            6 => Instruction::ReturnHome,
+            5 => Instruction::EntryModeSet { increment: flag(1), shift: flag(0) },
+            4 => Instruction::DisplayControl { display: flag(2), cursor: flag(1), blink: flag(0) },
+            3 => Instruction::Shift { display: flag(3), right: flag(2) },
+            2 => Instruction::FunctionSet { eight_bit: flag(4), two_lines: flag(3), five_by_ten: flag(2) },
+            1 => Instruction::SetCgramAddr(instruction & 0b0011_1111),
+            _ => Instruction::SetDdramAddr(instruction & 0b0111_1111),
+        }
+    }
+
+    // Name and fields as written in the datasheet, for the main log
+    fn name(&self) -> String {
+        let bit = |flag: bool| flag as u8;
+        match *self {
+            Instruction::Unknown => String::from("NONE (0x00)"),
+            Instruction::ClearDisplay => String::from("CLEAR_DISPLAY"),
+            Instruction::ReturnHome => String::from("RETURN_HOME"),
+            Instruction::EntryModeSet { increment, shift } => 
+                format!("ENTRY_MODE I/D={} S={}", bit(increment), bit(shift)),
+            Instruction::DisplayControl { display, cursor, blink } => 
+                format!("DISPLAY_CONTROL D={} C={} B={}", bit(display), bit(cursor), bit(blink)),
+            Instruction::Shift { display, right } => format!("SHIFT S/C={} R/L={}", bit(display), bit(right)),
+            Instruction::FunctionSet { eight_bit, two_lines, five_by_ten } => 
+                format!("FUNCTION_SET DL={} N={} F={}", bit(eight_bit), bit(two_lines), bit(five_by_ten)),
+            Instruction::SetCgramAddr(addr) => format!("SET_CGRAM_ADDR {:#04x}", addr),
+            Instruction::SetDdramAddr(addr) => format!("SET_DDRAM_ADDR {:#04x}", addr),
+        }
+    }
+
+    // What the instruction does, in words, for the LCD log
+    fn description(&self) -> String {
+        let on_off = |flag: bool| if flag { "on" } else { "off" };
+        match *self {
+            Instruction::Unknown => String::from("Unknown instruction 0x00"),
+            Instruction::ClearDisplay => String::from("Clear display"),
+            Instruction::ReturnHome => String::from("Return home"),
+            Instruction::EntryModeSet { increment, shift } => format!("Entry mode set: {}, {}",
+                if increment { "increment" } else { "decrement" }, if shift { "display shift" } else { "no display shift" }),
+            Instruction::DisplayControl { display, cursor, blink } => 
+                format!("Display on/off control: display {}, cursor {}, blink {}", on_off(display), on_off(cursor), on_off(blink)),
+            Instruction::Shift { display, right } => 
+                format!("{} shift {}", if display { "Display" } else { "Cursor" }, if right { "right" } else { "left" }),
+            Instruction::FunctionSet { eight_bit, two_lines, five_by_ten } => format!("Function set: {}, {}, {}",
+                if eight_bit { "8-bit" } else { "4-bit" }, if two_lines { "2 lines" } else { "1 line" }, 
+                if five_by_ten { "5x10" } else { "5x8" }),
+            Instruction::SetCgramAddr(addr) => format!("Set CGRAM address {:#04x}", addr),
+            Instruction::SetDdramAddr(addr) => format!("Set DDRAM address {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver};
+
+    fn create_test_lcd() -> (LCD, Receiver<LogMessage>, Receiver<ToGuiMessage>) {
+        create_test_lcd_with(Geometry::default())
+    }
+
+    fn create_test_lcd_with(geometry: Geometry) -> (LCD, Receiver<LogMessage>, Receiver<ToGuiMessage>) {
+        let (tx_log_msgs, rx_log_msgs) = mpsc::channel();
+        let (tx_to_gui, rx_gui_msgs) = mpsc::channel();
+        (LCD::new(geometry, CharRom::a00(), 1_000_000, None, tx_log_msgs, tx_to_gui), rx_log_msgs, rx_gui_msgs)
+    }
+
+    // One E pulse, with the data on DB0-DB7 (or DB4-DB7 in 4-bit mode),
+    // long enough after the previous one for the LCD not to be busy anymore
+    fn pulse(lcd: &mut LCD, rs: bool, rw: bool, data: u8) -> Option<DataOutput> {
+        let cycle = lcd.cycle + 2000;
+        pulse_at(lcd, cycle, rs, rw, data)
+    }
+
+    fn pulse_at(lcd: &mut LCD, cycle: usize, rs: bool, rw: bool, data: u8) -> Option<DataOutput> {
+        lcd.pins.rs = rs;
+        lcd.pins.rw = rw;
+        if !rw {
+            for bit in 0..=7 {
+                if !lcd.waiting_for_lower_half || bit >= 4 {
+                    lcd.data_pin_change(bit, (data >> bit) & 1 == 1);
+                }
+            }
+        }
+        lcd.clock(cycle);
+        lcd.enable_pin_change(true);
+        let output = lcd.data_output;
+        lcd.clock(cycle + 1);
+        lcd.enable_pin_change(false);
+        assert_eq!(None, lcd.data_output, "The data pins should be released when E goes low");
+        output
+    }
+
+    #[test]
+    fn read_8bit() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, then write "AB" at 0x00
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, true, false, b'A');
+        pulse(&mut lcd, true, false, b'B');
+
+        assert_eq!(Some(DataOutput { data: 0x02, driven: 0xff }), pulse(&mut lcd, false, true, 0),
+            "Busy flag should be clear, with the address counter at 0x02");
+
+        // Set DDRAM address 0x00, then read both characters back
+        pulse(&mut lcd, false, false, 0b1000_0000);
+        assert_eq!(Some(DataOutput { data: b'A', driven: 0xff }), pulse(&mut lcd, true, true, 0));
+        assert_eq!(Some(DataOutput { data: b'B', driven: 0xff }), pulse(&mut lcd, true, true, 0));
+        assert_eq!(0x02, lcd.ddram_addr, "Reads should increment the address counter");
+        assert_eq!(0x00, lcd.display_addr, "Reads shouldn't shift the display");
+    }
+
+    #[test]
+    fn function_set_keeps_ddram() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, then write "A" at 0x00
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, true, false, b'A');
+
+        // Function set again, to switch to 1 line and the 5x10 font
+        pulse(&mut lcd, false, false, 0b0011_0100);
+        pulse(&mut lcd, false, false, 0b1000_0000);
+        assert_eq!(Some(DataOutput { data: b'A', driven: 0xff }), pulse(&mut lcd, true, true, 0));
+    }
+
+    #[test]
+    fn read_4bit() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 4-bit (sent as a single 8-bit transfer), then 4-bit, 2 lines
+        pulse(&mut lcd, false, false, 0b0010_0000);
+        pulse(&mut lcd, false, false, 0b0010_0000);
+        pulse(&mut lcd, false, false, 0b1000_0000);
+        // Write 0x5a at 0x00
+        pulse(&mut lcd, true, false, 0x50);
+        pulse(&mut lcd, true, false, 0xa0);
+
+        assert_eq!(Some(DataOutput { data: 0x00, driven: 0xf0 }), pulse(&mut lcd, false, true, 0));
+        assert_eq!(Some(DataOutput { data: 0x10, driven: 0xf0 }), pulse(&mut lcd, false, true, 0),
+            "The lower half of the address counter should be sent on DB4-DB7");
+
+        // Set DDRAM address 0x00, then read the character back
+        pulse(&mut lcd, false, false, 0x80);
+        pulse(&mut lcd, false, false, 0x00);
+        assert_eq!(Some(DataOutput { data: 0x50, driven: 0xf0 }), pulse(&mut lcd, true, true, 0));
+        assert_eq!(Some(DataOutput { data: 0xa0, driven: 0xf0 }), pulse(&mut lcd, true, true, 0));
+        assert_eq!(0x01, lcd.ddram_addr);
+    }
+
+    fn log_lines(rx_log_msgs: &Receiver<LogMessage>) -> Vec<String> {
+        rx_log_msgs.try_iter().filter_map(|msg| match msg {
+            LogMessage::Log(msg) => Some(msg),
+            _ => None,
+        }).collect()
+    }
+
+    fn warnings(rx_log_msgs: &Receiver<LogMessage>) -> Vec<String> {
+        log_lines(rx_log_msgs).into_iter().filter(|msg| msg.contains("WARNING")).collect()
+    }
+
+    #[test]
+    fn init_by_instruction() {
+        // The 4-bit wiring of the breadboard, with the LCD out of sync after a reset of the CPU alone
+        for &(four_bit, waiting_for_lower_half) in &[(false, false), (true, false), (true, true)] {
+            let (mut lcd, rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+            lcd.set_connected_data_pins(0xf0);
+            if four_bit {
+                lcd.config.data_length = DataLength::Four;
+            }
+            lcd.waiting_for_lower_half = waiting_for_lower_half;
+            let initial_state = (four_bit, waiting_for_lower_half);
+
+            for nibble in &[0x30, 0x30, 0x30, 0x20] {
+                pulse(&mut lcd, false, false, *nibble);
+            }
+            assert_eq!(DataLength::Four, lcd.config.data_length);
+            assert!(!lcd.waiting_for_lower_half, "The next transfer should be in sync from {:?}", initial_state);
+
+            // Function set: 4-bit, 2 lines, then write "A"
+            pulse(&mut lcd, false, false, 0x20);
+            pulse(&mut lcd, false, false, 0x80);
+            pulse(&mut lcd, true, false, 0x40);
+            pulse(&mut lcd, true, false, 0x10);
+            assert_eq!(b'A', lcd.ddram_data[0x00]);
+            let warnings = warnings(&rx_log_msgs);
+            assert!(warnings.iter().all(|msg| !msg.contains("out of sync") && !msg.contains("initialization")),
+                "Unexpected warnings from {:?}: {:?}", initial_state, warnings);
+        }
+    }
+
+    #[test]
+    fn out_of_sync_warnings() {
+        let (mut lcd, rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        lcd.set_connected_data_pins(0xf0);
+        // Straight to 4-bit mode, which works since the LCD was just powered on
+        pulse(&mut lcd, false, false, 0x20);
+        assert!(warnings(&rx_log_msgs)[0].contains("without the 0x3, 0x3, 0x3 initialization sequence"));
+        assert_eq!(NbLines::Two, lcd.config.nb_lines, "N should be read from DB3, held high by its pull-up");
+
+        // A data write cut in half by an instruction
+        pulse(&mut lcd, true, false, 0x40);
+        pulse(&mut lcd, false, false, 0x10);
+        assert!(warnings(&rx_log_msgs)[0].contains("halves of a 4-bit transfer have different RS/RW (1/0 then 0/0)"));
+
+        // Back to 8-bit mode, then a program still thinking it's in 4-bit mode
+        pulse(&mut lcd, false, false, 0x30);
+        pulse(&mut lcd, false, false, 0x30);
+        assert_eq!(DataLength::Eigth, lcd.config.data_length);
+        pulse(&mut lcd, true, false, 0x40);
+        assert!(warnings(&rx_log_msgs)[0].contains("data write 0x4f received in 8-bit mode with only DB4-DB7 connected"));
+    }
+
+    #[test]
+    fn illegal_instructions() {
+        let (mut lcd, rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, then set DDRAM address 0x41
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, false, false, 0b1100_0001);
+        rx_log_msgs.try_iter().count();
+
+        pulse(&mut lcd, false, false, 0x00);
+        assert!(warnings(&rx_log_msgs)[0].contains("instruction 0x00 isn't defined in the datasheet, dropped"));
+        pulse(&mut lcd, false, false, 0b1011_0000);
+        assert!(warnings(&rx_log_msgs)[0].contains("DDRAM address 0x30 doesn't exist in 2-line mode, dropped"));
+        assert_eq!(0x41, lcd.ddram_addr);
+
+        // Function set: 8-bit, 1 line
+        pulse(&mut lcd, false, false, 0b0011_0000);
+        pulse(&mut lcd, false, false, 0b1101_0000);
+        assert!(warnings(&rx_log_msgs)[0].contains("DDRAM address 0x50 doesn't exist in 1-line mode, dropped"));
+        pulse(&mut lcd, false, false, 0b1011_0000);
+        assert_eq!(0x30, lcd.ddram_addr);
+    }
+
+    #[test]
+    fn state() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, display on with a blinking cursor
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, false, false, 0b0000_1101);
+        for &char_code in b"Hello, world!" {
+            pulse(&mut lcd, true, false, char_code);
         }
+        // Set DDRAM address 0x42, then write a CGRAM character
+        pulse(&mut lcd, false, false, 0b1100_0010);
+        pulse(&mut lcd, true, false, 0x00);
+
+        assert_eq!("Hello, world!", lcd.line(0));
+        assert_eq!("  ⣿", lcd.line(1));
+
+        let state = lcd.state();
+        assert_eq!(vec![String::from("Hello, world!"), String::from("  ⣿")], state.lines);
+        assert_eq!(b'H', state.ddram[0x00]);
+        assert_eq!(0x43, state.cursor_addr);
+        assert_eq!(Some((1, 3)), state.cursor_position);
+        assert_eq!((true, false, true, 0x00), (state.display_on, state.cursor_on, state.blink_on, state.display_shift));
+
+        // Shift the display, the first column now showing address 0x27
+        pulse(&mut lcd, false, false, 0b0001_1000);
+        assert_eq!(Some((1, 4)), lcd.state().cursor_position);
+        assert_eq!(0x27, lcd.state().display_shift);
+        assert_eq!(" Hello, world!", lcd.line(0));
+
+        // Display off
+        pulse(&mut lcd, false, false, 0b0000_1000);
+        assert_eq!(vec![String::new(), String::new()], lcd.state().lines);
+    }
+
+    #[test]
+    fn log_diffs() {
+        let (mut lcd, rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, display on, entry mode: increment
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, false, false, 0b0000_1100);
+        pulse(&mut lcd, false, false, 0b0000_0110);
+        rx_log_msgs.try_iter().count();
+
+        pulse(&mut lcd, true, false, b'A');
+        let log = log_lines(&rx_log_msgs);
+        assert_eq!("\nLCD: DDRAM[0x00] <- 0x41 'A'", log[0]);
+        assert_eq!("\nLCD row 0: \"\" -> \"A\"", log[1]);
+        assert_eq!(format!("\n{}", lcd.screen), log[2], "The whole frame should follow the changed rows");
+        assert_eq!(3, log.len(), "The frame should be logged once");
+
+        // Moving the cursor doesn't change the text
+        pulse(&mut lcd, false, false, 0b1000_0101);
+        let log = log_lines(&rx_log_msgs);
+        assert_eq!(vec![String::from("\nLCD: SET_DDRAM_ADDR 0x05")], log);
+    }
+
+    #[test]
+    fn lcd_log() {
+        let (mut lcd, rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        let lcd_log = || -> Vec<String> {
+            rx_log_msgs.try_iter().filter_map(|msg| match msg {
+                LogMessage::LcdOperation(msg) => Some(msg),
+                _ => None,
+            }).collect()
+        };
+        lcd.enable_operation_log();
+        // Function set: 8-bit, 2 lines, at cycle 2000, then write an "e" at 0x41
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, false, false, 0b1100_0001);
+        pulse(&mut lcd, true, false, b'e');
+        pulse_at(&mut lcd, 6010, true, false, b'f');
+        pulse_at(&mut lcd, 6020, false, true, 0);
+
+        assert_eq!(vec![
+            "      2000: Function set: 8-bit, 2 lines, 5x8",
+            "      4001: Set DDRAM address 0x41",
+            "      6002: Write DDRAM[0x41] = 'e' (0x65)",
+            "      6010: Dropped, sent while busy: data write 0x66",
+            "      6020: Read busy flag = 1, address counter = 0x42",
+        ], lcd_log());
+        assert_eq!("Display on/off control: display on, cursor off, blink on", Instruction::decode(0x0d).description());
+        assert_eq!("Cursor shift left", Instruction::decode(0x10).description());
+        assert_eq!("Entry mode set: increment, no display shift", Instruction::decode(0x06).description());
+    }
+
+    #[test]
+    fn instruction_names() {
+        assert_eq!("CLEAR_DISPLAY", Instruction::decode(0x01).name());
+        assert_eq!("RETURN_HOME", Instruction::decode(0x03).name());
+        assert_eq!("ENTRY_MODE I/D=1 S=0", Instruction::decode(0x06).name());
+        assert_eq!("DISPLAY_CONTROL D=1 C=1 B=0", Instruction::decode(0x0e).name());
+        assert_eq!("SHIFT S/C=1 R/L=0", Instruction::decode(0x18).name());
+        assert_eq!("FUNCTION_SET DL=0 N=1 F=0", Instruction::decode(0x28).name());
+        assert_eq!("SET_CGRAM_ADDR 0x08", Instruction::decode(0x48).name());
+        assert_eq!("SET_DDRAM_ADDR 0x40", Instruction::decode(0xc0).name());
+        assert_eq!(Instruction::FunctionSet { eight_bit: false, two_lines: true, five_by_ten: false }, 
+            Instruction::decode(0x28));
+    }
+
+    #[test]
+    fn cgram() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines
+        pulse(&mut lcd, false, false, 0b0011_1000);
+
+        // Set CGRAM address 0x08, then define character 1 as a full left half
+        let glyph = [0b1_1100, 0b1_1100, 0b1_1100, 0b1_1100, 0b1_1100, 0b1_1100, 0b1_1100, 0b1_1100];
+        pulse(&mut lcd, false, false, 0b0100_1000);
+        for &pixels in glyph.iter() {
+            pulse(&mut lcd, true, false, pixels);
+        }
+        assert_eq!(Some(DataOutput { data: 0x10, driven: 0xff }), pulse(&mut lcd, false, true, 0),
+            "The address counter should have moved to the next character");
+
+        // Read the last row back, with the entry mode set to decrement
+        pulse(&mut lcd, false, false, 0b0000_0100);
+        pulse(&mut lcd, false, false, 0b0100_1111);
+        assert_eq!(Some(DataOutput { data: 0b1_1100, driven: 0xff }), pulse(&mut lcd, true, true, 0));
+        assert_eq!(0x0e, lcd.cgram_addr);
+
+        // Character codes 0x01 and 0x09 both show that glyph once written to DDRAM
+        pulse(&mut lcd, false, false, 0b0000_0110);
+        pulse(&mut lcd, false, false, 0b1000_0000);
+        pulse(&mut lcd, true, false, 0x01);
+        pulse(&mut lcd, true, false, 0x09);
+        assert_eq!(glyph, lcd.cgram_glyph(0x09));
+        assert!(lcd.screen.contains("║\u{2847}\u{2847}  "), "{}", lcd.screen);
+    }
+
+    #[test]
+    fn render() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, display on with the cursor, then write "A"
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, false, false, 0b0000_1110);
+        pulse(&mut lcd, true, false, b'A');
+
+        let frame = lcd.render();
+        assert_eq!((2 * FRAME_MARGIN + 16 * 6 - 1, 2 * FRAME_MARGIN + 2 * 9 - 1), (frame.width, frame.height));
+
+        let dots = |column: usize, line: usize| -> Vec<u8> {
+            (0..8).map(|y| (0..5).fold(0, |row, x| {
+                let pixel = frame.get(FRAME_MARGIN + column * 6 + x, FRAME_MARGIN + line * 9 + y);
+                (row << 1) | (pixel == Pixel::On) as u8
+            })).collect()
+        };
+        assert_eq!(CharRom::a00().glyph(b'A').to_vec(), dots(0, 0));
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 0x1f], dots(1, 0), "The cursor should be under the second character");
+        assert_eq!(Pixel::Off, frame.get(FRAME_MARGIN, FRAME_MARGIN + 9));
+        assert_eq!(Pixel::Background, frame.get(FRAME_MARGIN + 5, FRAME_MARGIN));
+    }
+
+    #[test]
+    fn screenshot() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        let path = std::env::temp_dir().join("lcd_screenshot_test.txt");
+        lcd.screenshot_path = Some(path.clone());
+        lcd.stop();
+        assert_eq!(lcd.render().to_half_blocks(), fs::read_to_string(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        // A path that can't be written is reported, the emulator still exits normally
+        lcd.screenshot_path = Some(path.join("missing_folder").join("screenshot.png"));
+        lcd.stop();
+    }
+
+    #[test]
+    fn render_5x10() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd_with(Geometry::parse("16x1").unwrap());
+        // Function set: 8-bit, 1 line, 5x10 font, then write a "g" with its descender
+        pulse(&mut lcd, false, false, 0b0011_0100);
+        pulse(&mut lcd, true, false, 0xe7);
+
+        assert_eq!(vec![0x00, 0x00, 0x0f, 0x11, 0x11, 0x11, 0x0f, 0x01, 0x01, 0x0e, 0x00], lcd.glyph(0xe7));
+        assert_eq!(2 * FRAME_MARGIN + 11, lcd.render().height);
+    }
+
+    #[test]
+    fn char_rom_a02() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        lcd.char_rom = CharRom::a02();
+        // Function set: 8-bit, 2 lines, then write a backslash and an "é"
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse(&mut lcd, true, false, 0x5c);
+        pulse(&mut lcd, true, false, 0xe9);
+
+        assert!(lcd.screen.contains("║\\é "), "The A02 ROM should be used for the text output");
+        assert_eq!(CharRom::a02().glyph(0xe9).to_vec(), lcd.glyph(0xe9));
+    }
+
+    #[test]
+    fn busy_period() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, then clear display at cycle 10 000
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse_at(&mut lcd, 10_000, false, false, 0b0000_0001);
+
+        // At 1 MHz, clearing the display takes 1520 cycles
+        assert_eq!(Some(DataOutput { data: 0x80, driven: 0xff }), pulse_at(&mut lcd, 11_000, false, true, 0),
+            "The busy flag should be set");
+        pulse_at(&mut lcd, 11_100, true, false, b'A');
+        assert_eq!(0x20, lcd.ddram_data[0], "The write should have been dropped");
+        assert_eq!(1, lcd.dropped_count);
+
+        assert_eq!(Some(DataOutput { data: 0x00, driven: 0xff }), pulse_at(&mut lcd, 11_520, false, true, 0));
+        pulse_at(&mut lcd, 11_530, true, false, b'A');
+        assert_eq!(b'A', lcd.ddram_data[0]);
+
+        // Other instructions take 37 cycles
+        assert_eq!(Some(DataOutput { data: 0x81, driven: 0xff }), pulse_at(&mut lcd, 11_566, false, true, 0));
+        assert_eq!(Some(DataOutput { data: 0x01, driven: 0xff }), pulse_at(&mut lcd, 11_567, false, true, 0));
+    }
+
+    #[test]
+    fn blink() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd();
+        // Function set: 8-bit, 2 lines, then display on with a blinking cursor
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        pulse_at(&mut lcd, 10_000, false, false, 0b0000_1101);
+        assert_eq!(BlinkState::Off, lcd.current_blink_state);
+
+        // At 1 MHz, the cursor changes state every 409 600 cycles
+        lcd.clock(10_000 + 409_599);
+        assert_eq!(BlinkState::Off, lcd.current_blink_state);
+        lcd.clock(10_000 + 409_600);
+        assert_eq!(BlinkState::On, lcd.current_blink_state);
+        lcd.clock(10_000 + 2 * 409_600);
+        assert_eq!(BlinkState::Off, lcd.current_blink_state);
+    }
+
+    #[test]
+    fn geometry_20x4() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd_with(Geometry::parse("20x4").unwrap());
+        // Function set: 8-bit, 2 lines, then write a character at the start of each row
+        pulse(&mut lcd, false, false, 0b0011_1000);
+        for (addr, char_code) in [(0x00, b'0'), (0x40, b'1'), (0x14, b'2'), (0x54, b'3')].iter() {
+            pulse(&mut lcd, false, false, 0b1000_0000 | addr);
+            pulse(&mut lcd, true, false, *char_code);
+        }
+
+        let rows: Vec<&str> = lcd.screen.lines().collect();
+        assert_eq!(6, rows.len());
+        assert_eq!(format!("║0{}║", " ".repeat(19)), rows[1]);
+        assert_eq!(format!("║1{}║", " ".repeat(19)), rows[2]);
+        assert_eq!(format!("║2{}║", " ".repeat(19)), rows[3]);
+        assert_eq!(format!("║3{}║", " ".repeat(19)), rows[4]);
+
+        // Shifting the display moves the start of row 3 to the end of row 1
+        pulse(&mut lcd, false, false, 0b0001_1100);
+        assert_eq!(Some(0x14), lcd.displayed_addr(0, 19));
+        assert_eq!(Some(0x01), lcd.displayed_addr(0, 0));
+        assert_eq!(Some(0x15), lcd.displayed_addr(2, 0));
+        assert_eq!(Some(0x00), lcd.displayed_addr(2, 19));
+        assert_eq!(2 * FRAME_MARGIN + 4 * 9 - 1, lcd.render().height);
+    }
+
+    #[test]
+    fn geometry_one_line_mode() {
+        let (mut lcd, _rx_log_msgs, _rx_gui_msgs) = create_test_lcd_with(Geometry::parse("40x2").unwrap());
+        // Function set: 8-bit, 1 line, so the 80 characters of DDRAM are a single line
+        pulse(&mut lcd, false, false, 0b0011_0000);
+        assert_eq!(Some(0x27), lcd.displayed_addr(0, 39));
+        assert_eq!(None, lcd.displayed_addr(1, 0));
+
+        pulse(&mut lcd, false, false, 0b0001_1100);
+        assert_eq!(Some(0x28), lcd.displayed_addr(0, 39));
     }
 }
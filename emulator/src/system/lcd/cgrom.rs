@@ -0,0 +1,748 @@
+/// A character generator ROM: the dots of every character code,
+/// and the character standing for it in the text output.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CharRom {
+    chars: Vec<char>,
+    glyphs: Vec<[u8; 8]>,
+    descenders: Vec<(u8, [u8; 2])>,
+}
+
+impl CharRom {
+    /// Japanese standard font, with katakana, found on most modules.
+    pub fn a00() -> CharRom {
+        CharRom {
+            chars: CHARS_A00.to_vec(),
+            glyphs: CGROM_A00.to_vec(),
+            descenders: CGROM_A00_DESCENDERS.to_vec(),
+        }
+    }
+
+    /// European standard font, with accented letters and Cyrillic.
+    pub fn a02() -> CharRom {
+        CharRom {
+            chars: CHARS_A02.to_vec(),
+            glyphs: CGROM_A02.to_vec(),
+            descenders: Vec::new(),
+        }
+    }
+
+    /// Custom ROM described by a table file, for the modules with a mask-programmed font.
+    ///
+    /// An optional first line `base A00` or `base A02` selects the ROM the table starts from (A00 by default).
+    /// Each following line replaces one character: its hex code, the character printed in the text output
+    /// (or `U+` and its hex code point, for spaces and `#`), then its 8 dot rows in hex, top row first,
+    /// with the leftmost dot in bit 4, and optionally 2 more rows shown below it with the 5x10 font.
+    /// Everything after a `#` is a comment.
+    /// ```text
+    /// base A02
+    /// 7f ↵ 01 01 05 09 1f 08 04 00
+    /// ```
+    pub fn parse(text: &str) -> Result<CharRom, String> {
+        let mut rom = CharRom::a00();
+        let mut first_line = true;
+
+        for (line_nb, line) in text.lines().enumerate() {
+            let err = |msg: String| Err(format!("line {}: {}", line_nb + 1, msg));
+
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words[0].eq_ignore_ascii_case("base") {
+                if !first_line {
+                    return err(String::from("the base ROM must be selected before any character"));
+                }
+                rom = match words.get(1).map(|name| name.to_ascii_uppercase()).as_deref() {
+                    Some("A00") if words.len() == 2 => CharRom::a00(),
+                    Some("A02") if words.len() == 2 => CharRom::a02(),
+                    _ => return err(String::from("expected \"base A00\" or \"base A02\"")),
+                };
+                first_line = false;
+                continue;
+            }
+            first_line = false;
+
+            let code = match u8::from_str_radix(words[0], 16) {
+                Ok(code) if code >= 0x10 => code,
+                Ok(_) => return err(String::from("codes 00 to 0f are the CGRAM characters")),
+                Err(_) => return err(format!("invalid character code \"{}\"", words[0])),
+            };
+            let character = match words.get(1) {
+                Some(word) if word.chars().count() == 1 => word.chars().next().unwrap(),
+                Some(word) if word.to_ascii_uppercase().starts_with("U+") => {
+                    match u32::from_str_radix(&word[2..], 16).ok().and_then(std::char::from_u32) {
+                        Some(character) => character,
+                        None => return err(format!("invalid code point \"{}\"", word)),
+                    }
+                },
+                Some(word) => return err(format!("\"{}\" isn't a single character", word)),
+                None => return err(String::from("missing text character")),
+            };
+
+            let mut rows = Vec::new();
+            for word in &words[2..] {
+                match u8::from_str_radix(word, 16) {
+                    Ok(row) if row <= 0x1f => rows.push(row),
+                    _ => return err(format!("invalid dot row \"{}\" (expected 00 to 1f)", word)),
+                }
+            }
+            if rows.len() != 8 && rows.len() != 10 {
+                return err(format!("expected 8 or 10 dot rows, got {}", rows.len()));
+            }
+
+            rom.chars[code as usize] = character;
+            rom.glyphs[code as usize].copy_from_slice(&rows[..8]);
+            rom.descenders.retain(|(descender_code, _)| *descender_code != code);
+            if rows.len() == 10 {
+                rom.descenders.push((code, [rows[8], rows[9]]));
+            }
+        }
+
+        Ok(rom)
+    }
+
+    /// Character standing for that code in the text output.
+    pub fn char(&self, char_code: u8) -> char {
+        self.chars[char_code as usize]
+    }
+
+    /// The 8 dot rows of that code, the last one being the cursor line.
+    pub fn glyph(&self, char_code: u8) -> [u8; 8] {
+        self.glyphs[char_code as usize]
+    }
+
+    /// The 2 rows shown below the character with the 5x10 font, blank for the characters without a descender.
+    pub fn descender(&self, char_code: u8) -> [u8; 2] {
+        self.descenders.iter()
+            .find(|(code, _)| *code == char_code)
+            .map_or([0x00, 0x00], |(_, descender)| *descender)
+    }
+}
+
+// HD44780U character generator ROM, A00 (Japanese standard font) version.
+//
+// Each character is 8 rows of 5 pixels, top row first, with the leftmost pixel in bit 4.
+// The last row is where the cursor is drawn, so it is only used by characters with a descender.
+// Codes 0x00-0x0f are the CGRAM characters, and the unused codes are blank.
+pub const CGROM_A00: [[u8; 8]; 256] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 00 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 01 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 02 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 03 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 04 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 05 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 06 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 07 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 08 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 09 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0a CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0b CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0c CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0d CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0e CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0f CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 10
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 11
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 12
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 13
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 14
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 15
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 16
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 17
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 18
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 19
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 1a
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 1b
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 1c
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 1d
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 1e
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 1f
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 20
+    [0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x04, 0x00], // 21 !
+    [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00], // 22 "
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a, 0x00], // 23 #
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04, 0x00], // 24 $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03, 0x00], // 25 %
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d, 0x00], // 26 &
+    [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // 27 '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02, 0x00], // 28 (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08, 0x00], // 29 )
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00, 0x00], // 2a *
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00, 0x00], // 2b +
+    [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08, 0x00], // 2c ,
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00, 0x00], // 2d -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // 2e .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00, 0x00], // 2f /
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e, 0x00], // 30 0
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e, 0x00], // 31 1
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f, 0x00], // 32 2
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e, 0x00], // 33 3
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02, 0x00], // 34 4
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e, 0x00], // 35 5
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e, 0x00], // 36 6
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08, 0x00], // 37 7
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e, 0x00], // 38 8
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c, 0x00], // 39 9
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00, 0x00], // 3a :
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08, 0x00], // 3b ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02, 0x00], // 3c <
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00, 0x00], // 3d =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08, 0x00], // 3e >
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04, 0x00], // 3f ?
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e, 0x00], // 40 @
+    [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x00], // 41 A
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e, 0x00], // 42 B
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e, 0x00], // 43 C
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c, 0x00], // 44 D
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f, 0x00], // 45 E
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10, 0x00], // 46 F
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f, 0x00], // 47 G
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11, 0x00], // 48 H
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e, 0x00], // 49 I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c, 0x00], // 4a J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11, 0x00], // 4b K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f, 0x00], // 4c L
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11, 0x00], // 4d M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11, 0x00], // 4e N
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // 4f O
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10, 0x00], // 50 P
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d, 0x00], // 51 Q
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11, 0x00], // 52 R
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e, 0x00], // 53 S
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // 54 T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // 55 U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04, 0x00], // 56 V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a, 0x00], // 57 W
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11, 0x00], // 58 X
+    [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x00], // 59 Y
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f, 0x00], // 5a Z
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e, 0x00], // 5b [
+    [0x11, 0x0a, 0x1f, 0x04, 0x1f, 0x04, 0x04, 0x00], // 5c ¥
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e, 0x00], // 5d ]
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00], // 5e ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x00], // 5f _
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00], // 60 `
+    [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // 61 a
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e, 0x00], // 62 b
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e, 0x00], // 63 c
+    [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f, 0x00], // 64 d
+    [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e, 0x00], // 65 e
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08, 0x00], // 66 f
+    [0x00, 0x0f, 0x11, 0x11, 0x0f, 0x01, 0x0e, 0x00], // 67 g
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11, 0x00], // 68 h
+    [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e, 0x00], // 69 i
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c, 0x00], // 6a j
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12, 0x00], // 6b k
+    [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e, 0x00], // 6c l
+    [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11, 0x00], // 6d m
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11, 0x00], // 6e n
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // 6f o
+    [0x00, 0x00, 0x1e, 0x11, 0x1e, 0x10, 0x10, 0x00], // 70 p
+    [0x00, 0x00, 0x0d, 0x13, 0x0f, 0x01, 0x01, 0x00], // 71 q
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10, 0x00], // 72 r
+    [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e, 0x00], // 73 s
+    [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06, 0x00], // 74 t
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // 75 u
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04, 0x00], // 76 v
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a, 0x00], // 77 w
+    [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x00], // 78 x
+    [0x00, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e, 0x00], // 79 y
+    [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f, 0x00], // 7a z
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02, 0x00], // 7b {
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // 7c |
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08, 0x00], // 7d }
+    [0x00, 0x04, 0x02, 0x1f, 0x02, 0x04, 0x00, 0x00], // 7e →
+    [0x00, 0x04, 0x08, 0x1f, 0x08, 0x04, 0x00, 0x00], // 7f ←
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 80
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 81
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 82
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 83
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 84
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 85
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 86
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 87
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 88
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 89
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 8a
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 8b
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 8c
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 8d
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 8e
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 8f
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 90
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 91
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 92
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 93
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 94
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 95
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 96
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 97
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 98
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 99
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 9a
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 9b
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 9c
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 9d
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 9e
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 9f
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // a0
+    [0x00, 0x00, 0x00, 0x00, 0x1c, 0x14, 0x1c, 0x00], // a1 ｡
+    [0x07, 0x04, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00], // a2 ｢
+    [0x00, 0x00, 0x00, 0x04, 0x04, 0x04, 0x1c, 0x00], // a3 ｣
+    [0x00, 0x00, 0x00, 0x00, 0x10, 0x08, 0x04, 0x00], // a4 ､
+    [0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00, 0x00, 0x00], // a5 ･
+    [0x00, 0x1f, 0x01, 0x1f, 0x01, 0x02, 0x04, 0x00], // a6 ｦ
+    [0x00, 0x00, 0x1f, 0x01, 0x06, 0x04, 0x08, 0x00], // a7 ｧ
+    [0x00, 0x00, 0x02, 0x04, 0x0c, 0x14, 0x04, 0x00], // a8 ｨ
+    [0x00, 0x00, 0x04, 0x1f, 0x11, 0x01, 0x06, 0x00], // a9 ｩ
+    [0x00, 0x00, 0x00, 0x1f, 0x04, 0x04, 0x1f, 0x00], // aa ｪ
+    [0x00, 0x00, 0x02, 0x1f, 0x06, 0x0a, 0x12, 0x00], // ab ｫ
+    [0x00, 0x00, 0x08, 0x1f, 0x09, 0x0a, 0x08, 0x00], // ac ｬ
+    [0x00, 0x00, 0x00, 0x0e, 0x02, 0x02, 0x1f, 0x00], // ad ｭ
+    [0x00, 0x00, 0x1e, 0x02, 0x1e, 0x02, 0x1e, 0x00], // ae ｮ
+    [0x00, 0x00, 0x00, 0x15, 0x15, 0x01, 0x06, 0x00], // af ｯ
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00, 0x00], // b0 ｰ
+    [0x1f, 0x01, 0x05, 0x06, 0x04, 0x04, 0x08, 0x00], // b1 ｱ
+    [0x01, 0x02, 0x04, 0x0c, 0x14, 0x04, 0x04, 0x00], // b2 ｲ
+    [0x04, 0x1f, 0x11, 0x11, 0x01, 0x02, 0x04, 0x00], // b3 ｳ
+    [0x00, 0x1f, 0x04, 0x04, 0x04, 0x04, 0x1f, 0x00], // b4 ｴ
+    [0x02, 0x1f, 0x02, 0x06, 0x0a, 0x12, 0x02, 0x00], // b5 ｵ
+    [0x08, 0x1f, 0x09, 0x09, 0x09, 0x09, 0x12, 0x00], // b6 ｶ
+    [0x04, 0x1f, 0x04, 0x1f, 0x04, 0x04, 0x04, 0x00], // b7 ｷ
+    [0x00, 0x0f, 0x09, 0x11, 0x01, 0x02, 0x0c, 0x00], // b8 ｸ
+    [0x08, 0x0f, 0x12, 0x02, 0x02, 0x02, 0x04, 0x00], // b9 ｹ
+    [0x00, 0x1f, 0x01, 0x01, 0x01, 0x01, 0x1f, 0x00], // ba ｺ
+    [0x0a, 0x1f, 0x0a, 0x0a, 0x02, 0x04, 0x08, 0x00], // bb ｻ
+    [0x00, 0x18, 0x01, 0x19, 0x01, 0x02, 0x1c, 0x00], // bc ｼ
+    [0x00, 0x1f, 0x01, 0x02, 0x04, 0x0a, 0x11, 0x00], // bd ｽ
+    [0x08, 0x1f, 0x09, 0x0a, 0x08, 0x08, 0x07, 0x00], // be ｾ
+    [0x00, 0x11, 0x11, 0x09, 0x01, 0x02, 0x0c, 0x00], // bf ｿ
+    [0x00, 0x0f, 0x09, 0x15, 0x03, 0x02, 0x0c, 0x00], // c0 ﾀ
+    [0x02, 0x1c, 0x04, 0x1f, 0x04, 0x04, 0x08, 0x00], // c1 ﾁ
+    [0x00, 0x15, 0x15, 0x15, 0x01, 0x02, 0x04, 0x00], // c2 ﾂ
+    [0x0e, 0x00, 0x1f, 0x04, 0x04, 0x04, 0x08, 0x00], // c3 ﾃ
+    [0x08, 0x08, 0x08, 0x0c, 0x0a, 0x08, 0x08, 0x00], // c4 ﾄ
+    [0x04, 0x04, 0x1f, 0x04, 0x04, 0x08, 0x10, 0x00], // c5 ﾅ
+    [0x00, 0x0e, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x00], // c6 ﾆ
+    [0x00, 0x1f, 0x01, 0x0a, 0x04, 0x0a, 0x10, 0x00], // c7 ﾇ
+    [0x04, 0x1f, 0x02, 0x04, 0x0e, 0x15, 0x04, 0x00], // c8 ﾈ
+    [0x02, 0x02, 0x02, 0x02, 0x02, 0x04, 0x08, 0x00], // c9 ﾉ
+    [0x00, 0x04, 0x02, 0x11, 0x11, 0x11, 0x11, 0x00], // ca ﾊ
+    [0x10, 0x10, 0x1f, 0x10, 0x10, 0x10, 0x0f, 0x00], // cb ﾋ
+    [0x00, 0x1f, 0x01, 0x01, 0x01, 0x02, 0x0c, 0x00], // cc ﾌ
+    [0x00, 0x08, 0x14, 0x02, 0x01, 0x01, 0x00, 0x00], // cd ﾍ
+    [0x04, 0x1f, 0x04, 0x04, 0x15, 0x15, 0x04, 0x00], // ce ﾎ
+    [0x00, 0x1f, 0x01, 0x01, 0x0a, 0x04, 0x02, 0x00], // cf ﾏ
+    [0x00, 0x0e, 0x00, 0x0e, 0x00, 0x0e, 0x01, 0x00], // d0 ﾐ
+    [0x00, 0x04, 0x08, 0x10, 0x11, 0x1f, 0x01, 0x00], // d1 ﾑ
+    [0x00, 0x01, 0x01, 0x0a, 0x04, 0x0a, 0x10, 0x00], // d2 ﾒ
+    [0x00, 0x1f, 0x08, 0x1f, 0x08, 0x08, 0x07, 0x00], // d3 ﾓ
+    [0x08, 0x08, 0x1f, 0x09, 0x0a, 0x08, 0x08, 0x00], // d4 ﾔ
+    [0x00, 0x0e, 0x02, 0x02, 0x02, 0x02, 0x1f, 0x00], // d5 ﾕ
+    [0x00, 0x1f, 0x01, 0x1f, 0x01, 0x01, 0x1f, 0x00], // d6 ﾖ
+    [0x0e, 0x00, 0x1f, 0x01, 0x01, 0x02, 0x04, 0x00], // d7 ﾗ
+    [0x12, 0x12, 0x12, 0x12, 0x02, 0x04, 0x08, 0x00], // d8 ﾘ
+    [0x00, 0x04, 0x14, 0x14, 0x15, 0x15, 0x16, 0x00], // d9 ﾙ
+    [0x00, 0x10, 0x10, 0x11, 0x12, 0x14, 0x18, 0x00], // da ﾚ
+    [0x00, 0x1f, 0x11, 0x11, 0x11, 0x11, 0x1f, 0x00], // db ﾛ
+    [0x00, 0x1f, 0x11, 0x11, 0x01, 0x02, 0x04, 0x00], // dc ﾜ
+    [0x00, 0x18, 0x00, 0x01, 0x01, 0x02, 0x1c, 0x00], // dd ﾝ
+    [0x04, 0x12, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // de ﾞ
+    [0x1c, 0x14, 0x1c, 0x00, 0x00, 0x00, 0x00, 0x00], // df ﾟ
+    [0x00, 0x00, 0x09, 0x15, 0x12, 0x12, 0x0d, 0x00], // e0 α
+    [0x0a, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e1 ä
+    [0x00, 0x00, 0x0e, 0x11, 0x1e, 0x11, 0x11, 0x1e], // e2 β
+    [0x00, 0x00, 0x0e, 0x10, 0x0c, 0x11, 0x0e, 0x00], // e3 ε
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x11, 0x13, 0x1d], // e4 μ
+    [0x00, 0x00, 0x0f, 0x14, 0x12, 0x11, 0x0e, 0x00], // e5 σ
+    [0x00, 0x00, 0x06, 0x09, 0x11, 0x11, 0x11, 0x1e], // e6 ρ
+    [0x00, 0x00, 0x0f, 0x11, 0x11, 0x11, 0x0f, 0x01], // e7 g
+    [0x00, 0x00, 0x07, 0x04, 0x04, 0x14, 0x08, 0x00], // e8 √
+    [0x00, 0x02, 0x1a, 0x02, 0x00, 0x00, 0x00, 0x00], // e9 ¹
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x02, 0x02, 0x02], // ea j
+    [0x00, 0x14, 0x08, 0x14, 0x00, 0x00, 0x00, 0x00], // eb ˣ
+    [0x00, 0x04, 0x0e, 0x14, 0x15, 0x0e, 0x04, 0x00], // ec ¢
+    [0x08, 0x08, 0x1c, 0x08, 0x1c, 0x08, 0x0f, 0x00], // ed Ⱡ
+    [0x0e, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11, 0x00], // ee ñ
+    [0x0a, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // ef ö
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11, 0x1e], // f0 p
+    [0x00, 0x00, 0x0d, 0x13, 0x11, 0x11, 0x11, 0x0f], // f1 q
+    [0x00, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x0e, 0x00], // f2 θ
+    [0x00, 0x00, 0x00, 0x0b, 0x15, 0x1a, 0x00, 0x00], // f3 ∞
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x0a, 0x1b, 0x00], // f4 Ω
+    [0x0a, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // f5 ü
+    [0x1f, 0x10, 0x08, 0x04, 0x08, 0x10, 0x1f, 0x00], // f6 Σ
+    [0x00, 0x00, 0x1f, 0x0a, 0x0a, 0x0a, 0x13, 0x00], // f7 π
+    [0x1f, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x00], // f8 𝔵
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0f], // f9 y
+    [0x00, 0x01, 0x1e, 0x04, 0x1f, 0x04, 0x04, 0x00], // fa 千
+    [0x00, 0x00, 0x1f, 0x08, 0x0f, 0x09, 0x11, 0x00], // fb 万
+    [0x00, 0x00, 0x1f, 0x15, 0x1f, 0x11, 0x11, 0x00], // fc 円
+    [0x00, 0x00, 0x04, 0x00, 0x1f, 0x00, 0x04, 0x00], // fd ÷
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // fe
+    [0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f], // ff █
+];
+
+// The two extra rows of the characters with a descender, only displayed with the 5x10 font
+// (the characters without one are blank below their 8th row)
+pub const CGROM_A00_DESCENDERS: [(u8, [u8; 2]); 8] = [
+    (0xe2, [0x10, 0x10]), // β
+    (0xe4, [0x10, 0x10]), // μ
+    (0xe6, [0x10, 0x10]), // ρ
+    (0xe7, [0x01, 0x0e]), // g
+    (0xea, [0x12, 0x0c]), // j
+    (0xf0, [0x10, 0x10]), // p
+    (0xf1, [0x01, 0x01]), // q
+    (0xf9, [0x01, 0x0e]), // y
+];
+
+// HD44780U character generator ROM, A02 (European standard font) version, in the same format.
+// The characters it shares with the A00 ROM have the same dots.
+pub const CGROM_A02: [[u8; 8]; 256] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 00 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 01 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 02 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 03 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 04 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 05 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 06 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 07 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 08 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 09 CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0a CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0b CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0c CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0d CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0e CGRAM
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0f CGRAM
+    [0x08, 0x0c, 0x0e, 0x0f, 0x0e, 0x0c, 0x08, 0x00], // 10 ▶
+    [0x02, 0x06, 0x0e, 0x1e, 0x0e, 0x06, 0x02, 0x00], // 11 ◀
+    [0x09, 0x12, 0x1b, 0x00, 0x00, 0x00, 0x00, 0x00], // 12 “
+    [0x1b, 0x09, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00], // 13 ”
+    [0x04, 0x0e, 0x1f, 0x04, 0x0e, 0x1f, 0x04, 0x00], // 14 ⇈
+    [0x04, 0x1f, 0x0e, 0x04, 0x1f, 0x0e, 0x04, 0x00], // 15 ⇊
+    [0x00, 0x0e, 0x1f, 0x1f, 0x1f, 0x0e, 0x00, 0x00], // 16 ●
+    [0x00, 0x01, 0x05, 0x0d, 0x1f, 0x0c, 0x04, 0x00], // 17 ↵
+    [0x04, 0x0e, 0x15, 0x04, 0x04, 0x04, 0x04, 0x00], // 18 ↑
+    [0x04, 0x04, 0x04, 0x04, 0x15, 0x0e, 0x04, 0x00], // 19 ↓
+    [0x00, 0x04, 0x02, 0x1f, 0x02, 0x04, 0x00, 0x00], // 1a →
+    [0x00, 0x04, 0x08, 0x1f, 0x08, 0x04, 0x00, 0x00], // 1b ←
+    [0x02, 0x04, 0x08, 0x04, 0x02, 0x00, 0x1f, 0x00], // 1c ≤
+    [0x08, 0x04, 0x02, 0x04, 0x08, 0x00, 0x1f, 0x00], // 1d ≥
+    [0x00, 0x04, 0x04, 0x0e, 0x0e, 0x1f, 0x1f, 0x00], // 1e ▲
+    [0x1f, 0x1f, 0x0e, 0x0e, 0x04, 0x04, 0x00, 0x00], // 1f ▼
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 20
+    [0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x04, 0x00], // 21 !
+    [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00], // 22 "
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a, 0x00], // 23 #
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04, 0x00], // 24 $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03, 0x00], // 25 %
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d, 0x00], // 26 &
+    [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // 27 '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02, 0x00], // 28 (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08, 0x00], // 29 )
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00, 0x00], // 2a *
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00, 0x00], // 2b +
+    [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08, 0x00], // 2c ,
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00, 0x00], // 2d -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // 2e .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00, 0x00], // 2f /
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e, 0x00], // 30 0
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e, 0x00], // 31 1
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f, 0x00], // 32 2
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e, 0x00], // 33 3
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02, 0x00], // 34 4
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e, 0x00], // 35 5
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e, 0x00], // 36 6
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08, 0x00], // 37 7
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e, 0x00], // 38 8
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c, 0x00], // 39 9
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00, 0x00], // 3a :
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08, 0x00], // 3b ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02, 0x00], // 3c <
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00, 0x00], // 3d =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08, 0x00], // 3e >
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04, 0x00], // 3f ?
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e, 0x00], // 40 @
+    [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x00], // 41 A
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e, 0x00], // 42 B
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e, 0x00], // 43 C
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c, 0x00], // 44 D
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f, 0x00], // 45 E
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10, 0x00], // 46 F
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f, 0x00], // 47 G
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11, 0x00], // 48 H
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e, 0x00], // 49 I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c, 0x00], // 4a J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11, 0x00], // 4b K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f, 0x00], // 4c L
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11, 0x00], // 4d M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11, 0x00], // 4e N
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // 4f O
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10, 0x00], // 50 P
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d, 0x00], // 51 Q
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11, 0x00], // 52 R
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e, 0x00], // 53 S
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // 54 T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // 55 U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04, 0x00], // 56 V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a, 0x00], // 57 W
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11, 0x00], // 58 X
+    [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x00], // 59 Y
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f, 0x00], // 5a Z
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e, 0x00], // 5b [
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00, 0x00], // 5c \
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e, 0x00], // 5d ]
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00], // 5e ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x00], // 5f _
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00], // 60 `
+    [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // 61 a
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e, 0x00], // 62 b
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e, 0x00], // 63 c
+    [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f, 0x00], // 64 d
+    [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e, 0x00], // 65 e
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08, 0x00], // 66 f
+    [0x00, 0x0f, 0x11, 0x11, 0x0f, 0x01, 0x0e, 0x00], // 67 g
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11, 0x00], // 68 h
+    [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e, 0x00], // 69 i
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c, 0x00], // 6a j
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12, 0x00], // 6b k
+    [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e, 0x00], // 6c l
+    [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11, 0x00], // 6d m
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11, 0x00], // 6e n
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // 6f o
+    [0x00, 0x00, 0x1e, 0x11, 0x1e, 0x10, 0x10, 0x00], // 70 p
+    [0x00, 0x00, 0x0d, 0x13, 0x0f, 0x01, 0x01, 0x00], // 71 q
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10, 0x00], // 72 r
+    [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e, 0x00], // 73 s
+    [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06, 0x00], // 74 t
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // 75 u
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04, 0x00], // 76 v
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a, 0x00], // 77 w
+    [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x00], // 78 x
+    [0x00, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e, 0x00], // 79 y
+    [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f, 0x00], // 7a z
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02, 0x00], // 7b {
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // 7c |
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08, 0x00], // 7d }
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00, 0x00], // 7e ~
+    [0x00, 0x04, 0x0a, 0x11, 0x11, 0x11, 0x1f, 0x00], // 7f ⌂
+    [0x1f, 0x10, 0x10, 0x1e, 0x11, 0x11, 0x1e, 0x00], // 80 Б
+    [0x0e, 0x0a, 0x0a, 0x0a, 0x0a, 0x1f, 0x11, 0x00], // 81 Д
+    [0x15, 0x15, 0x15, 0x0e, 0x15, 0x15, 0x15, 0x00], // 82 Ж
+    [0x0e, 0x11, 0x01, 0x06, 0x01, 0x11, 0x0e, 0x00], // 83 З
+    [0x11, 0x11, 0x13, 0x15, 0x19, 0x11, 0x11, 0x00], // 84 И
+    [0x0a, 0x04, 0x11, 0x13, 0x15, 0x19, 0x11, 0x00], // 85 Й
+    [0x07, 0x09, 0x09, 0x09, 0x09, 0x09, 0x11, 0x00], // 86 Л
+    [0x1f, 0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x00], // 87 П
+    [0x11, 0x11, 0x11, 0x0f, 0x01, 0x11, 0x0e, 0x00], // 88 У
+    [0x12, 0x12, 0x12, 0x12, 0x12, 0x1f, 0x01, 0x00], // 89 Ц
+    [0x11, 0x11, 0x11, 0x0f, 0x01, 0x01, 0x01, 0x00], // 8a Ч
+    [0x15, 0x15, 0x15, 0x15, 0x15, 0x15, 0x1f, 0x00], // 8b Ш
+    [0x15, 0x15, 0x15, 0x15, 0x15, 0x1f, 0x01, 0x00], // 8c Щ
+    [0x18, 0x08, 0x08, 0x0e, 0x09, 0x09, 0x0e, 0x00], // 8d Ъ
+    [0x11, 0x11, 0x11, 0x19, 0x15, 0x15, 0x19, 0x00], // 8e Ы
+    [0x0e, 0x11, 0x01, 0x07, 0x01, 0x11, 0x0e, 0x00], // 8f Э
+    [0x00, 0x00, 0x09, 0x15, 0x12, 0x12, 0x0d, 0x00], // 90 α
+    [0x04, 0x06, 0x05, 0x04, 0x04, 0x1c, 0x18, 0x00], // 91 ♪
+    [0x1f, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // 92 Γ
+    [0x00, 0x00, 0x1f, 0x0a, 0x0a, 0x0a, 0x13, 0x00], // 93 π
+    [0x1f, 0x10, 0x08, 0x04, 0x08, 0x10, 0x1f, 0x00], // 94 Σ
+    [0x00, 0x00, 0x0f, 0x14, 0x12, 0x11, 0x0e, 0x00], // 95 σ
+    [0x0f, 0x09, 0x09, 0x09, 0x0b, 0x1b, 0x18, 0x00], // 96 ♬
+    [0x00, 0x00, 0x0f, 0x14, 0x04, 0x04, 0x06, 0x00], // 97 τ
+    [0x04, 0x0e, 0x0e, 0x0e, 0x1f, 0x00, 0x04, 0x00], // 98 ⍾
+    [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x0e, 0x00], // 99 Θ
+    [0x0e, 0x11, 0x11, 0x11, 0x0a, 0x0a, 0x1b, 0x00], // 9a Ω
+    [0x06, 0x08, 0x04, 0x0e, 0x11, 0x11, 0x0e, 0x00], // 9b δ
+    [0x00, 0x00, 0x00, 0x0b, 0x15, 0x1a, 0x00, 0x00], // 9c ∞
+    [0x00, 0x0a, 0x1f, 0x1f, 0x0e, 0x04, 0x00, 0x00], // 9d ♥
+    [0x00, 0x00, 0x0e, 0x10, 0x0c, 0x11, 0x0e, 0x00], // 9e ε
+    [0x00, 0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x00], // 9f ∩
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // a0
+    [0x04, 0x00, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // a1 ¡
+    [0x00, 0x04, 0x0e, 0x14, 0x15, 0x0e, 0x04, 0x00], // a2 ¢
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x09, 0x16, 0x00], // a3 £
+    [0x00, 0x11, 0x0e, 0x0a, 0x0e, 0x11, 0x00, 0x00], // a4 ¤
+    [0x11, 0x0a, 0x1f, 0x04, 0x1f, 0x04, 0x04, 0x00], // a5 ¥
+    [0x04, 0x04, 0x04, 0x00, 0x04, 0x04, 0x04, 0x00], // a6 ¦
+    [0x0e, 0x10, 0x0e, 0x11, 0x0e, 0x01, 0x0e, 0x00], // a7 §
+    [0x03, 0x04, 0x04, 0x0e, 0x04, 0x04, 0x18, 0x00], // a8 ƒ
+    [0x0e, 0x11, 0x17, 0x15, 0x17, 0x11, 0x0e, 0x00], // a9 ©
+    [0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00, 0x1f, 0x00], // aa ª
+    [0x00, 0x05, 0x0a, 0x14, 0x0a, 0x05, 0x00, 0x00], // ab «
+    [0x12, 0x15, 0x15, 0x1d, 0x15, 0x15, 0x12, 0x00], // ac Ю
+    [0x0f, 0x11, 0x11, 0x0f, 0x05, 0x09, 0x11, 0x00], // ad Я
+    [0x0e, 0x11, 0x1d, 0x1b, 0x15, 0x11, 0x0e, 0x00], // ae ®
+    [0x04, 0x08, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00], // af ‘
+    [0x0c, 0x12, 0x12, 0x0c, 0x00, 0x00, 0x00, 0x00], // b0 °
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x1f, 0x00], // b1 ±
+    [0x0c, 0x12, 0x04, 0x08, 0x1e, 0x00, 0x00, 0x00], // b2 ²
+    [0x1c, 0x02, 0x0c, 0x02, 0x1c, 0x00, 0x00, 0x00], // b3 ³
+    [0x1c, 0x12, 0x1c, 0x17, 0x12, 0x12, 0x11, 0x00], // b4 ₧
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x11, 0x13, 0x1d], // b5 µ
+    [0x0f, 0x1d, 0x1d, 0x0d, 0x05, 0x05, 0x05, 0x00], // b6 ¶
+    [0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00], // b7 ·
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a, 0x00], // b8 ω
+    [0x08, 0x18, 0x08, 0x08, 0x1c, 0x00, 0x00, 0x00], // b9 ¹
+    [0x0e, 0x11, 0x11, 0x0e, 0x00, 0x1f, 0x00, 0x00], // ba º
+    [0x00, 0x14, 0x0a, 0x05, 0x0a, 0x14, 0x00, 0x00], // bb »
+    [0x10, 0x11, 0x12, 0x04, 0x0a, 0x17, 0x02, 0x00], // bc ¼
+    [0x10, 0x11, 0x12, 0x04, 0x0b, 0x11, 0x07, 0x00], // bd ½
+    [0x18, 0x09, 0x1a, 0x04, 0x0a, 0x17, 0x02, 0x00], // be ¾
+    [0x04, 0x00, 0x04, 0x08, 0x10, 0x11, 0x0e, 0x00], // bf ¿
+    [0x08, 0x04, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x00], // c0 À
+    [0x02, 0x04, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x00], // c1 Á
+    [0x04, 0x0a, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x00], // c2 Â
+    [0x0d, 0x16, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x00], // c3 Ã
+    [0x0a, 0x00, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x00], // c4 Ä
+    [0x04, 0x0a, 0x0e, 0x11, 0x1f, 0x11, 0x11, 0x00], // c5 Å
+    [0x0f, 0x14, 0x14, 0x1f, 0x14, 0x14, 0x17, 0x00], // c6 Æ
+    [0x0e, 0x11, 0x10, 0x10, 0x11, 0x0e, 0x04, 0x0c], // c7 Ç
+    [0x08, 0x04, 0x1f, 0x10, 0x1e, 0x10, 0x1f, 0x00], // c8 È
+    [0x02, 0x04, 0x1f, 0x10, 0x1e, 0x10, 0x1f, 0x00], // c9 É
+    [0x04, 0x0a, 0x1f, 0x10, 0x1e, 0x10, 0x1f, 0x00], // ca Ê
+    [0x0a, 0x00, 0x1f, 0x10, 0x1e, 0x10, 0x1f, 0x00], // cb Ë
+    [0x08, 0x04, 0x0e, 0x04, 0x04, 0x04, 0x0e, 0x00], // cc Ì
+    [0x02, 0x04, 0x0e, 0x04, 0x04, 0x04, 0x0e, 0x00], // cd Í
+    [0x04, 0x0a, 0x0e, 0x04, 0x04, 0x04, 0x0e, 0x00], // ce Î
+    [0x0a, 0x00, 0x0e, 0x04, 0x04, 0x04, 0x0e, 0x00], // cf Ï
+    [0x1c, 0x12, 0x11, 0x1d, 0x11, 0x12, 0x1c, 0x00], // d0 Ð
+    [0x0d, 0x16, 0x11, 0x19, 0x15, 0x13, 0x11, 0x00], // d1 Ñ
+    [0x08, 0x04, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // d2 Ò
+    [0x02, 0x04, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // d3 Ó
+    [0x04, 0x0a, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // d4 Ô
+    [0x0d, 0x16, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // d5 Õ
+    [0x0a, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // d6 Ö
+    [0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x00, 0x00], // d7 ×
+    [0x0e, 0x13, 0x15, 0x15, 0x15, 0x19, 0x0e, 0x00], // d8 Ø
+    [0x08, 0x04, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // d9 Ù
+    [0x02, 0x04, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // da Ú
+    [0x04, 0x0a, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // db Û
+    [0x0a, 0x00, 0x11, 0x11, 0x11, 0x11, 0x0e, 0x00], // dc Ü
+    [0x02, 0x04, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x00], // dd Ý
+    [0x10, 0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x00], // de Þ
+    [0x00, 0x00, 0x0e, 0x11, 0x1e, 0x11, 0x11, 0x1e], // df ß
+    [0x08, 0x04, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e0 à
+    [0x02, 0x04, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e1 á
+    [0x04, 0x0a, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e2 â
+    [0x0d, 0x16, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e3 ã
+    [0x0a, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e4 ä
+    [0x04, 0x0a, 0x0e, 0x01, 0x0f, 0x11, 0x0f, 0x00], // e5 å
+    [0x00, 0x00, 0x1a, 0x05, 0x0f, 0x14, 0x1b, 0x00], // e6 æ
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e, 0x0c], // e7 ç
+    [0x08, 0x04, 0x0e, 0x11, 0x1f, 0x10, 0x0e, 0x00], // e8 è
+    [0x02, 0x04, 0x0e, 0x11, 0x1f, 0x10, 0x0e, 0x00], // e9 é
+    [0x04, 0x0a, 0x0e, 0x11, 0x1f, 0x10, 0x0e, 0x00], // ea ê
+    [0x0a, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e, 0x00], // eb ë
+    [0x08, 0x04, 0x0c, 0x04, 0x04, 0x04, 0x0e, 0x00], // ec ì
+    [0x02, 0x04, 0x0c, 0x04, 0x04, 0x04, 0x0e, 0x00], // ed í
+    [0x04, 0x0a, 0x0c, 0x04, 0x04, 0x04, 0x0e, 0x00], // ee î
+    [0x0a, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e, 0x00], // ef ï
+    [0x0a, 0x04, 0x0a, 0x01, 0x0f, 0x11, 0x0e, 0x00], // f0 ð
+    [0x0d, 0x16, 0x16, 0x19, 0x11, 0x11, 0x11, 0x00], // f1 ñ
+    [0x08, 0x04, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // f2 ò
+    [0x02, 0x04, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // f3 ó
+    [0x04, 0x0a, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // f4 ô
+    [0x0d, 0x16, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // f5 õ
+    [0x0a, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00], // f6 ö
+    [0x00, 0x00, 0x04, 0x00, 0x1f, 0x00, 0x04, 0x00], // f7 ÷
+    [0x00, 0x00, 0x0e, 0x13, 0x15, 0x19, 0x0e, 0x00], // f8 ø
+    [0x08, 0x04, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // f9 ù
+    [0x02, 0x04, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // fa ú
+    [0x04, 0x0a, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // fb û
+    [0x0a, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d, 0x00], // fc ü
+    [0x02, 0x04, 0x11, 0x11, 0x0f, 0x01, 0x0e, 0x00], // fd ý
+    [0x10, 0x10, 0x1e, 0x11, 0x11, 0x1e, 0x10, 0x00], // fe þ
+    [0x0a, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e, 0x00], // ff ÿ
+];
+
+// Characters standing for the A00 codes in the text output
+const CHARS_A00: [char; 256] = [
+    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '¥', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '→', '←',
+    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+    ' ', '｡', '｢', '｣', '､', '･', 'ｦ', 'ｧ', 'ｨ', 'ｩ', 'ｪ', 'ｫ', 'ｬ', 'ｭ', 'ｮ', 'ｯ',
+    'ｰ', 'ｱ', 'ｲ', 'ｳ', 'ｴ', 'ｵ', 'ｶ', 'ｷ', 'ｸ', 'ｹ', 'ｺ', 'ｻ', 'ｼ', 'ｽ', 'ｾ', 'ｿ',
+    'ﾀ', 'ﾁ', 'ﾂ', 'ﾃ', 'ﾄ', 'ﾅ', 'ﾆ', 'ﾇ', 'ﾈ', 'ﾉ', 'ﾊ', 'ﾋ', 'ﾌ', 'ﾍ', 'ﾎ', 'ﾏ',
+    'ﾐ', 'ﾑ', 'ﾒ', 'ﾓ', 'ﾔ', 'ﾕ', 'ﾖ', 'ﾗ', 'ﾘ', 'ﾙ', 'ﾚ', 'ﾛ', 'ﾜ', 'ﾝ', 'ﾞ', 'ﾟ',
+    'α', 'ä', 'β', 'ε', 'μ', 'σ', 'ρ', 'g', '√', '¹', 'j', 'ˣ', '¢', 'Ⱡ', 'ñ', 'ö',
+    'p', 'q', 'θ', '∞', 'Ω', 'ü', 'Σ', 'π', '𝔵', 'y', '千', '万', '円', '÷', ' ', '█',
+];
+
+// Characters standing for the A02 codes in the text output
+const CHARS_A02: [char; 256] = [
+    ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+    '▶', '◀', '“', '”', '⇈', '⇊', '●', '↵', '↑', '↓', '→', '←', '≤', '≥', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Б', 'Д', 'Ж', 'З', 'И', 'Й', 'Л', 'П', 'У', 'Ц', 'Ч', 'Ш', 'Щ', 'Ъ', 'Ы', 'Э',
+    'α', '♪', 'Γ', 'π', 'Σ', 'σ', '♬', 'τ', '⍾', 'Θ', 'Ω', 'δ', '∞', '♥', 'ε', '∩',
+    ' ', '¡', '¢', '£', '¤', '¥', '¦', '§', 'ƒ', '©', 'ª', '«', 'Ю', 'Я', '®', '‘',
+    '°', '±', '²', '³', '₧', 'µ', '¶', '·', 'ω', '¹', 'º', '»', '¼', '½', '¾', '¿',
+    'À', 'Á', 'Â', 'Ã', 'Ä', 'Å', 'Æ', 'Ç', 'È', 'É', 'Ê', 'Ë', 'Ì', 'Í', 'Î', 'Ï',
+    'Ð', 'Ñ', 'Ò', 'Ó', 'Ô', 'Õ', 'Ö', '×', 'Ø', 'Ù', 'Ú', 'Û', 'Ü', 'Ý', 'Þ', 'ß',
+    'à', 'á', 'â', 'ã', 'ä', 'å', 'æ', 'ç', 'è', 'é', 'ê', 'ë', 'ì', 'í', 'î', 'ï',
+    'ð', 'ñ', 'ò', 'ó', 'ô', 'õ', 'ö', '÷', 'ø', 'ù', 'ú', 'û', 'ü', 'ý', 'þ', 'ÿ',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_roms() {
+        assert_eq!('¥', CharRom::a00().char(0x5c));
+        assert_eq!('\\', CharRom::a02().char(0x5c));
+        assert_eq!(CharRom::a00().glyph(b'A'), CharRom::a02().glyph(b'A'));
+        assert_ne!(CharRom::a00().glyph(0xe1), CharRom::a02().glyph(0xe1));
+        assert_eq!([0x01, 0x0e], CharRom::a00().descender(0xe7));
+        assert_eq!([0x00, 0x00], CharRom::a02().descender(0xe7));
+    }
+
+    #[test]
+    fn parse_rom() {
+        let rom = CharRom::parse("
+            base a02    # case doesn't matter
+            7f ↵ 01 01 05 09 1f 08 04 00
+            80 U+0020 1f 1f 1f 1f 1f 1f 1f 1f 03 03
+        ").unwrap();
+
+        assert_eq!('ä', rom.char(0xe4));
+        assert_eq!('↵', rom.char(0x7f));
+        assert_eq!([0x01, 0x01, 0x05, 0x09, 0x1f, 0x08, 0x04, 0x00], rom.glyph(0x7f));
+        assert_eq!(' ', rom.char(0x80));
+        assert_eq!([0x03, 0x03], rom.descender(0x80));
+
+        assert_eq!(CharRom::a00().glyph(0xe0), CharRom::parse("").unwrap().glyph(0xe0));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(String::from("line 2: the base ROM must be selected before any character")),
+            CharRom::parse("41 A 00 00 00 00 00 00 00 00\nbase A02"));
+        assert_eq!(Err(String::from("line 1: expected \"base A00\" or \"base A02\"")),
+            CharRom::parse("base A01"));
+        assert_eq!(Err(String::from("line 1: codes 00 to 0f are the CGRAM characters")),
+            CharRom::parse("05 A 00 00 00 00 00 00 00 00"));
+        assert_eq!(Err(String::from("line 1: \"AB\" isn't a single character")),
+            CharRom::parse("41 AB 00 00 00 00 00 00 00 00"));
+        assert_eq!(Err(String::from("line 1: invalid dot row \"20\" (expected 00 to 1f)")),
+            CharRom::parse("41 A 20 00 00 00 00 00 00 00"));
+        assert_eq!(Err(String::from("line 1: expected 8 or 10 dot rows, got 9")),
+            CharRom::parse("41 A 00 00 00 00 00 00 00 00 00"));
+    }
+}
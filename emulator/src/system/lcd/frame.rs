@@ -0,0 +1,191 @@
+/// State of one point of a rendered LCD frame.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pixel {
+    // Between the dots of the matrix
+    Background,
+    Off,
+    On,
+}
+
+impl Pixel {
+    // Yellow-green backlight with dark dots, like the usual 16x2 modules
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Pixel::Background => [0x8f, 0xb8, 0x2a],
+            Pixel::Off => [0x80, 0xa8, 0x22],
+            Pixel::On => [0x1e, 0x2c, 0x12],
+        }
+    }
+}
+
+/// A picture of the LCD glass, one `Pixel` per dot.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Pixel>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Frame {
+        Frame {
+            width,
+            height,
+            pixels: vec![Pixel::Background; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Pixel {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, pixel: Pixel) {
+        self.pixels[y * self.width + x] = pixel;
+    }
+
+    // RGB bytes of the frame, each pixel being drawn as a scale x scale square
+    fn to_rgb(&self, scale: usize) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(self.width * self.height * scale * scale * 3);
+        for y in 0..self.height * scale {
+            for x in 0..self.width * scale {
+                rgb.extend_from_slice(&self.get(x / scale, y / scale).rgb());
+            }
+        }
+        rgb
+    }
+
+    /// Binary PPM (P6) picture of the frame.
+    pub fn to_ppm(&self, scale: usize) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width * scale, self.height * scale).into_bytes();
+        ppm.extend(self.to_rgb(scale));
+        ppm
+    }
+
+    /// PNG picture of the frame, stored without compression.
+    pub fn to_png(&self, scale: usize) -> Vec<u8> {
+        let (width, height) = (self.width * scale, self.height * scale);
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+        // 8 bits per channel, RGB, default compression, filter and interlace methods
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        // Each scanline starts with its filter type, 0 being none
+        let rgb = self.to_rgb(scale);
+        let mut scanlines = Vec::with_capacity(rgb.len() + height);
+        for line in rgb.chunks(width * 3) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(line);
+        }
+
+        let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        png_chunk(&mut png, b"IHDR", &ihdr);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Text picture of the frame for terminals, each character showing two pixels on top of each other.
+    pub fn to_half_blocks(&self) -> String {
+        let mut text = String::new();
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let top = self.get(x, y) == Pixel::On;
+                let bottom = y + 1 < self.height && self.get(x, y + 1) == Pixel::On;
+                text.push(match (top, bottom) {
+                    (false, false) => ' ',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (true, true) => '█',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// zlib stream made of uncompressed deflate blocks, which is enough for small pictures
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    zlib
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_frame() -> Frame {
+        let mut frame = Frame::new(3, 3);
+        frame.set(0, 0, Pixel::On);
+        frame.set(1, 1, Pixel::On);
+        frame.set(2, 0, Pixel::Off);
+        frame.set(2, 2, Pixel::On);
+        frame
+    }
+
+    #[test]
+    fn half_blocks() {
+        assert_eq!("▀▄ \n  ▀\n", test_frame().to_half_blocks());
+    }
+
+    #[test]
+    fn ppm() {
+        let ppm = test_frame().to_ppm(2);
+        assert!(ppm.starts_with(b"P6\n6 6\n255\n"));
+        assert_eq!(11 + 6 * 6 * 3, ppm.len());
+        // Second pixel of the first line is still the top-left dot
+        assert_eq!(Pixel::On.rgb(), ppm[14..17]);
+    }
+
+    #[test]
+    fn png() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+
+        let png = test_frame().to_png(1);
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        assert_eq!(b"IHDR", &png[12..16]);
+        assert_eq!([0, 0, 0, 3, 0, 0, 0, 3], png[16..24]);
+        // Empty IEND chunk, with its well-known CRC
+        assert_eq!([0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82], png[png.len() - 12..]);
+    }
+}
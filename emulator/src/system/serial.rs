@@ -0,0 +1,267 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// Where the other end of the ACIA serial line is, on the host.
+#[derive(Clone, PartialEq, Debug)]
+pub enum HostPort {
+    // The console the emulator runs in
+    Stdio,
+    // A new pseudo-terminal, to open with a terminal program such as screen or minicom
+    Pty,
+    // A socket listening on localhost, for one client at a time
+    Tcp(u16),
+}
+
+impl HostPort {
+    /// `stdio`, `pty` or `tcp:<port>`
+    pub fn parse(name: &str) -> Option<HostPort> {
+        match name.to_ascii_lowercase().as_str() {
+            "stdio" => Some(HostPort::Stdio),
+            "pty" => Some(HostPort::Pty),
+            name if name.starts_with("tcp:") => name[4..].parse::<u16>().ok().map(HostPort::Tcp),
+            _ => None,
+        }
+    }
+}
+
+/// The host side of the serial line: the bytes received from the host are read by a
+/// background thread and queued, and the transmitted bytes are written as soon as they are sent.
+pub struct SerialBridge {
+    rx_bytes: Receiver<u8>,
+    tx: Box<dyn Write + Send>,
+}
+
+impl SerialBridge {
+    pub fn open(port: &HostPort) -> SerialBridge {
+        let (tx_bytes, rx_bytes) = mpsc::channel();
+
+        let tx: Box<dyn Write + Send> = match port {
+            HostPort::Stdio => {
+                forward_bytes(io::stdin(), tx_bytes);
+                Box::new(io::stdout())
+            },
+            HostPort::Pty => {
+                let (pty, pty_name) = open_pty();
+                println!("ACIA serial port on {}", pty_name);
+                forward_bytes(PtyReader(pty.try_clone().expect("Failed to clone the pseudo-terminal")), tx_bytes);
+                Box::new(pty)
+            },
+            HostPort::Tcp(port) => {
+                let listener = TcpListener::bind(("127.0.0.1", *port))
+                    .unwrap_or_else(|err| panic!("Failed to listen on localhost:{}, {}", port, err));
+                println!("ACIA serial port on localhost:{}", port);
+                let client = TcpClient::default();
+                accept_clients(listener, client.clone(), tx_bytes);
+                Box::new(client)
+            },
+        };
+
+        SerialBridge { rx_bytes, tx }
+    }
+
+    /// A bridge to the other end of a channel, for the tests.
+    #[cfg(test)]
+    pub fn with_channels(rx_bytes: Receiver<u8>, tx: Box<dyn Write + Send>) -> SerialBridge {
+        SerialBridge { rx_bytes, tx }
+    }
+
+    /// The next byte sent by the host, if any.
+    pub fn try_recv(&self) -> Option<u8> {
+        self.rx_bytes.try_recv().ok()
+    }
+
+    pub fn send(&mut self, byte: u8) {
+        // The host going away doesn't stop the system, like an unplugged cable
+        let _ = self.tx.write_all(&[byte]).and_then(|_| self.tx.flush());
+    }
+}
+
+// Queue the bytes of a reader from a background thread, until it is closed
+fn forward_bytes<R: Read + Send + 'static>(mut reader: R, tx_bytes: Sender<u8>) {
+    thread::Builder::new().name("serial thread".to_string()).spawn(move || {
+        let mut buf = [0; 256];
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => len,
+            };
+            if buf[..len].iter().any(|&byte| tx_bytes.send(byte).is_err()) {
+                break;
+            }
+        }
+    }).unwrap();
+}
+
+// Master side of a pseudo-terminal: reading it fails with EIO while no terminal program has the
+// other side open, e.g. after screen or minicom is closed, so it waits for the next one instead
+struct PtyReader(std::fs::File);
+
+impl Read for PtyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // EIO has the same value on every Unix
+        const EIO: i32 = 5;
+
+        loop {
+            match self.0.read(buf) {
+                Err(err) if err.raw_os_error() == Some(EIO) => thread::sleep(Duration::from_millis(100)),
+                result => return result,
+            }
+        }
+    }
+}
+
+// The TCP client currently connected, the bytes sent while there is none are dropped
+#[derive(Clone, Default)]
+struct TcpClient(Arc<Mutex<Option<TcpStream>>>);
+
+impl Write for TcpClient {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.lock().unwrap().as_mut() {
+            Some(stream) => stream.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.lock().unwrap().as_mut() {
+            Some(stream) => stream.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn accept_clients(listener: TcpListener, client: TcpClient, tx_bytes: Sender<u8>) {
+    thread::Builder::new().name("serial listener thread".to_string()).spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let reader = match stream.try_clone() {
+                Ok(reader) => reader,
+                Err(_) => continue,
+            };
+            // A new client replaces the previous one
+            *client.0.lock().unwrap() = Some(stream);
+            forward_bytes(reader, Sender::clone(&tx_bytes));
+        }
+    }).unwrap();
+}
+
+#[cfg(unix)]
+fn open_pty() -> (std::fs::File, String) {
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_int};
+    use std::os::unix::io::FromRawFd;
+
+    // struct termios, only handled by the C library, so left opaque (and larger than on any Unix)
+    #[repr(C, align(8))]
+    struct Termios([u8; 256]);
+
+    extern "C" {
+        fn posix_openpt(flags: c_int) -> c_int;
+        fn grantpt(fd: c_int) -> c_int;
+        fn unlockpt(fd: c_int) -> c_int;
+        fn ptsname(fd: c_int) -> *const c_char;
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, optional_actions: c_int, termios: *const Termios) -> c_int;
+        fn cfmakeraw(termios: *mut Termios);
+    }
+    // O_RDWR
+    const READ_WRITE: c_int = 2;
+    const TCSANOW: c_int = 0;
+
+    unsafe {
+        let fd = posix_openpt(READ_WRITE);
+        assert!(fd >= 0, "Failed to open a pseudo-terminal");
+        assert!(grantpt(fd) == 0 && unlockpt(fd) == 0, "Failed to unlock the pseudo-terminal");
+
+        // Raw mode, so that the bytes go through unchanged (no echo, no \n to \r\n, no line editing)
+        let mut termios = Termios([0; 256]);
+        assert!(tcgetattr(fd, &mut termios) == 0, "Failed to get the pseudo-terminal attributes");
+        cfmakeraw(&mut termios);
+        assert!(tcsetattr(fd, TCSANOW, &termios) == 0, "Failed to set the pseudo-terminal in raw mode");
+
+        let name = ptsname(fd);
+        assert!(!name.is_null(), "Failed to get the name of the pseudo-terminal");
+        (std::fs::File::from_raw_fd(fd), CStr::from_ptr(name).to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(not(unix))]
+fn open_pty() -> (std::fs::File, String) {
+    panic!("Pseudo-terminals are only available on Unix, use stdio or tcp:<port> instead")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_host_port() {
+        assert_eq!(Some(HostPort::Stdio), HostPort::parse("stdio"));
+        assert_eq!(Some(HostPort::Pty), HostPort::parse("PTY"));
+        assert_eq!(Some(HostPort::Tcp(6551)), HostPort::parse("tcp:6551"));
+        assert_eq!(None, HostPort::parse("tcp:65536"));
+        assert_eq!(None, HostPort::parse("com1"));
+    }
+
+    #[test]
+    fn tcp_bridge() {
+        // Port 0 can't be used with HostPort, so the listener is set up by hand
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx_bytes, rx_bytes) = mpsc::channel();
+        let client = TcpClient::default();
+        accept_clients(listener, client.clone(), tx_bytes);
+        let mut bridge = SerialBridge::with_channels(rx_bytes, Box::new(client.clone()));
+
+        // Nobody is connected yet
+        bridge.send(b'?');
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        while client.0.lock().unwrap().is_none() {
+            thread::yield_now();
+        }
+        stream.write_all(b"hi").unwrap();
+        let received: Vec<u8> = (0..2).map(|_| bridge.rx_bytes.recv().unwrap()).collect();
+        assert_eq!(b"hi".to_vec(), received);
+
+        bridge.send(b'!');
+        let mut buf = [0; 1];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(*b"!", buf);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pty_bridge() {
+        use std::fs::OpenOptions;
+
+        let (pty, pty_name) = open_pty();
+        let (tx_bytes, rx_bytes) = mpsc::channel();
+        forward_bytes(PtyReader(pty.try_clone().unwrap()), tx_bytes);
+        let mut bridge = SerialBridge::with_channels(rx_bytes, Box::new(pty));
+
+        // The terminal program can come and go, the bytes always go through unchanged
+        for _ in 0..2 {
+            let mut terminal = OpenOptions::new().read(true).write(true).open(&pty_name).unwrap();
+            terminal.write_all(b"hi\n").unwrap();
+            let received: Vec<u8> = (0..3).map(|_| bridge.rx_bytes.recv().unwrap()).collect();
+            assert_eq!(b"hi\n".to_vec(), received);
+
+            bridge.send(b'\n');
+            let mut buf = [0; 1];
+            terminal.read_exact(&mut buf).unwrap();
+            assert_eq!(*b"\n", buf);
+
+            // Long enough for the reading thread to notice the terminal is gone
+            drop(terminal);
+            thread::sleep(Duration::from_millis(200));
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use super::inputs::InputCommand;
+use super::wiring::ViaPin;
+
+/// What makes the next step of a timeline happen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Trigger {
+    // The cycle count reaches that value
+    At(usize),
+    // That many cycles went by since the previous step
+    After(usize),
+    // The CPU fetches an opcode at that address
+    Pc(u16),
+}
+
+/// What a step of a timeline does.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Action {
+    Input(InputCommand),
+    // Drive a VIA pin (of the first VIA when there is no address) to a level, or stop driving it with None
+    Pull(Option<u16>, ViaPin, Option<bool>),
+    // Stop the CPU, as if it executed an STP
+    Stop,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Step {
+    trigger: Trigger,
+    action: Action,
+    // The line of the file, shown in the log when the step happens
+    text: String,
+}
+
+/// A script of input changes, applied by the SYS thread so that every run gets the same stimulus.
+///
+/// The file has one step per line, made of a trigger and an action, and the steps happen one after
+/// the other, in the order of the file. Everything after a `#` is a comment.
+/// ```text
+/// label wait_key 8012      # name an address, to use it in the following lines
+/// at 10000 pull PA3 low    # when the cycle count reaches 10000, drive PA3 of the first VIA low
+/// when pc wait_key press 0 # the next time the CPU fetches an opcode at $8012, press BTN.0
+/// after 5000 release 0     # 5000 cycles later, release it
+/// after 100 pull 5000.CA1 low
+/// after 100 free 5000.CA1  # stop driving the pin, which goes back to what the wiring sets
+/// at 2000000 stop          # stop the CPU
+/// ```
+/// The actions are the input commands (see `InputCommand`), `pull <pin> low|high`, `free <pin>` and `stop`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Timeline {
+    steps: Vec<Step>,
+    next_step: usize,
+    // Cycle on which the previous step happened
+    last_step_cycle: usize,
+}
+
+impl Timeline {
+    pub fn parse(text: &str) -> Result<Timeline, String> {
+        let mut timeline = Timeline::default();
+        let mut labels: Vec<(String, u16)> = Vec::new();
+
+        for (line_nb, line) in text.lines().enumerate() {
+            let err = |msg: String| Err(format!("line {}: {}", line_nb + 1, msg));
+
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            let number = |word: &str| word.parse::<usize>().ok();
+
+            let (trigger, action_words) = match words.as_slice() {
+                ["label", label, label_addr] => match u16::from_str_radix(label_addr.trim_start_matches('$'), 16) {
+                    Ok(label_addr) => {
+                        labels.push((label.to_string(), label_addr));
+                        continue;
+                    },
+                    Err(_) => return err(format!("invalid address \"{}\"", label_addr)),
+                },
+                ["at", cycle, action @ ..] => match number(cycle) {
+                    Some(cycle) => (Trigger::At(cycle), action),
+                    None => return err(format!("invalid cycle count \"{}\"", cycle)),
+                },
+                ["after", cycles, action @ ..] => match number(cycles) {
+                    Some(cycles) => (Trigger::After(cycles), action),
+                    None => return err(format!("invalid number of cycles \"{}\"", cycles)),
+                },
+                ["when", "pc", pc, action @ ..] => match labels.iter()
+                    .find(|(label, _)| label == pc)
+                    .map(|(_, addr)| *addr)
+                    .or_else(|| u16::from_str_radix(pc.trim_start_matches('$'), 16).ok())
+                {
+                    Some(pc) => (Trigger::Pc(pc), action),
+                    None => return err(format!("invalid address or unknown label \"{}\"", pc)),
+                },
+                _ => return err(String::from("expected \"at <cycle>\", \"after <cycles>\", \
+                    \"when pc <address>\" or \"label <name> <address>\"")),
+            };
+
+            let action = match action_words {
+                ["stop"] => Action::Stop,
+                ["pull", pin, level] => {
+                    let (via_addr, via_pin) = match parse_pin(pin) {
+                        Some(pin) => pin,
+                        None => return err(format!("unknown VIA pin \"{}\"", pin)),
+                    };
+                    match level.to_ascii_lowercase().as_str() {
+                        "low" => Action::Pull(via_addr, via_pin, Some(false)),
+                        "high" => Action::Pull(via_addr, via_pin, Some(true)),
+                        _ => return err(format!("invalid level \"{}\" (expected low or high)", level)),
+                    }
+                },
+                ["free", pin] => match parse_pin(pin) {
+                    Some((via_addr, via_pin)) => Action::Pull(via_addr, via_pin, None),
+                    None => return err(format!("unknown VIA pin \"{}\"", pin)),
+                },
+                [] => return err(String::from("missing action")),
+                _ => match InputCommand::parse(&action_words.join(" ")) {
+                    Ok(command) => Action::Input(command),
+                    Err(msg) => return err(msg),
+                },
+            };
+
+            timeline.steps.push(Step { trigger, action, text: line.to_string() });
+        }
+
+        Ok(timeline)
+    }
+
+    /// Addresses of the VIAs whose pins the timeline drives, apart from the first one.
+    pub fn via_addrs(&self) -> impl Iterator<Item = u16> + '_ {
+        self.steps.iter().filter_map(|step| match step.action {
+            Action::Pull(via_addr, _, _) => via_addr,
+            _ => None,
+        })
+    }
+
+    /// The steps happening on `cycle`, with `pc` the address of the opcode being fetched if any,
+    /// as the action and the line of the file.
+    pub fn due(&mut self, cycle: usize, pc: Option<u16>) -> Vec<(Action, String)> {
+        let mut actions = Vec::new();
+
+        while let Some(step) = self.steps.get(self.next_step) {
+            let happens = match step.trigger {
+                Trigger::At(step_cycle) => cycle >= step_cycle,
+                Trigger::After(cycles) => cycle >= self.last_step_cycle + cycles,
+                Trigger::Pc(step_pc) => pc == Some(step_pc),
+            };
+            if !happens {
+                break;
+            }
+
+            actions.push((step.action.clone(), step.text.clone()));
+            self.next_step += 1;
+            self.last_step_cycle = cycle;
+
+            // Two PC steps in a row wait for two fetches
+            if let Trigger::Pc(_) = step.trigger {
+                break;
+            }
+        }
+
+        actions
+    }
+}
+
+// "PA3" for a pin of the first VIA, or "5000.PA3"
+fn parse_pin(name: &str) -> Option<(Option<u16>, ViaPin)> {
+    let mut parts = name.rsplitn(2, '.');
+    let via_pin = ViaPin::parse(parts.next().unwrap())?;
+    match parts.next() {
+        Some(via_addr) => u16::from_str_radix(via_addr.trim_start_matches('$'), 16).ok()
+            .map(|via_addr| (Some(via_addr), via_pin)),
+        None => Some((None, via_pin)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timeline() {
+        let timeline = Timeline::parse("
+            # Wait for a key
+            label wait_key $8012
+            at 10000 pull PA3 low
+            when pc wait_key press 0
+            after 5000 Release 0   # case doesn't matter for the commands
+            after 100 free 5000.ca1
+            when pc 9000 stop
+        ").unwrap();
+
+        assert_eq!(vec![
+            (Trigger::At(10_000), Action::Pull(None, ViaPin::PA(3), Some(false))),
+            (Trigger::Pc(0x8012), Action::Input(InputCommand::Press(0))),
+            (Trigger::After(5_000), Action::Input(InputCommand::Release(0))),
+            (Trigger::After(100), Action::Pull(Some(0x5000), ViaPin::CA1, None)),
+            (Trigger::Pc(0x9000), Action::Stop),
+        ], timeline.steps.iter().map(|step| (step.trigger, step.action.clone())).collect::<Vec<_>>());
+        assert_eq!("at 10000 pull PA3 low", timeline.steps[0].text);
+        assert_eq!(vec![0x5000], timeline.via_addrs().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(String::from("line 1: invalid cycle count \"soon\"")),
+            Timeline::parse("at soon stop"));
+        assert_eq!(Err(String::from("line 2: invalid address or unknown label \"wait_key\"")),
+            Timeline::parse("\nwhen pc wait_key stop"));
+        assert_eq!(Err(String::from("line 1: unknown VIA pin \"PC3\"")),
+            Timeline::parse("at 0 pull PC3 low"));
+        assert_eq!(Err(String::from("line 1: invalid level \"up\" (expected low or high)")),
+            Timeline::parse("at 0 pull PA3 up"));
+        assert_eq!(Err(String::from("line 1: missing action")),
+            Timeline::parse("after 10"));
+        assert_eq!(Err(String::from("line 1: invalid input number \"9\" (expected 0 to 7)")),
+            Timeline::parse("after 10 press 9"));
+        assert!(Timeline::parse("sometime stop").unwrap_err().starts_with("line 1: expected \"at <cycle>\""));
+    }
+
+    #[test]
+    fn steps_in_order() {
+        let mut timeline = Timeline::parse("
+            at 100 press 0
+            when pc 8000 press 1
+            when pc 8000 press 2
+            after 10 release 0
+            after 0 stop
+        ").unwrap();
+        let commands = |actions: Vec<(Action, String)>| actions.into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>();
+
+        assert!(timeline.due(99, Some(0x8000)).is_empty(), "The PC steps wait for the previous ones");
+        assert_eq!(vec!["at 100 press 0"], commands(timeline.due(100, None)));
+        assert!(timeline.due(101, Some(0x8003)).is_empty());
+        assert_eq!(vec!["when pc 8000 press 1"], commands(timeline.due(102, Some(0x8000))));
+        assert_eq!(vec!["when pc 8000 press 2"], commands(timeline.due(110, Some(0x8000))));
+        assert!(timeline.due(119, None).is_empty());
+        assert_eq!(vec!["after 10 release 0", "after 0 stop"], commands(timeline.due(120, None)));
+        assert_eq!(timeline.steps.len(), timeline.next_step);
+    }
+}
@@ -19,10 +19,28 @@ pub trait ViaSystem {
     /// and `false` being ground.
     fn write_port_a(&mut self, via: &mut W65C22S, bit: u8, level: bool);
 
+    /// Called by the VIA to change the level of the CA2 pin.
+    fn write_ca2(&mut self, via: &mut W65C22S, level: bool);
+
+    /// Called by the VIA to change the level of the CB1 pin, 
+    /// when the shift register uses it as a clock output.
+    fn write_cb1(&mut self, via: &mut W65C22S, level: bool);
+
     /// Called by the VIA to change the level of the CB2 pin.
     fn write_cb2(&mut self, via: &mut W65C22S, level: bool);
 
-    // fn read_cb1(&mut self, via: &mut W65C22S) -> bool;
+    /// Called by the VIA on every cycle to get the electrical level of the CA1 pin, 
+    /// which is always an input.
+    fn read_ca1(&mut self, via: &mut W65C22S) -> bool;
+
+    /// Called by the VIA to get the electrical level of the CA2 pin when it is an input.
+    fn read_ca2(&mut self, via: &mut W65C22S) -> bool;
+
+    /// Called by the VIA to get the electrical level of the CB1 pin when it is an input.
+    fn read_cb1(&mut self, via: &mut W65C22S) -> bool;
+
+    /// Called by the VIA to get the electrical level of the CB2 pin when it is an input.
+    fn read_cb2(&mut self, via: &mut W65C22S) -> bool;
     
     /// Receive an update from the `IRQB` pin.
     /// The value recieved is the *logical* one, not the *electrical* one!
@@ -32,7 +50,7 @@ pub trait ViaSystem {
     /// 
     /// Also, when using multiple devices that can trigger an interrupt,
     /// this IRQ value must be logically ORed with that of other devices
-    /// before sending it to the processor.
+    /// before sending it to the processor (see `IrqLine`).
     fn update_irq(&mut self, via: &mut W65C22S, irq: bool);
 }
 
@@ -48,12 +66,14 @@ const T1L_L: u8 = 0x6;
 const T1L_H: u8 = 0x7;
 const T2C_L: u8 = 0x8;
 const T2C_H: u8 = 0x9;
+const SR: u8 = 0xa;
 const ACR: u8 = 0xb;
 const PCR: u8 = 0xc;
 const IFR: u8 = 0xd;
 const IER: u8 = 0xe;
+const PA_NO_HS: u8 = 0xf;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct W65C22S {
     ddra: u8,
     ddrb: u8,
@@ -65,12 +85,31 @@ pub struct W65C22S {
     cb2: bool,
     ca1: bool,
     ca2: bool,
+    // Control lines set low by a pulse output, to put back high on the next cycle
+    ca2_pulse: bool,
+    cb2_pulse: bool,
     t1_l: u16,
     t1_c: u16,
-    t1_is_running: bool, // Maybe it's actually running all the time and there's an interrupt disable flag instead, to test
+    // The counters always run, these flags only allow a timeout to set the interrupt flag
+    t1_trigger_interrupt: bool,
+    // Set on the cycle during which the counter is loaded instead of decremented
+    t1_load: bool,
+    // Free-run mode reloads the counter on the cycle following the timeout
+    t1_reload: bool,
+    // Level output on PB7 when ACR7 = 1
+    t1_pb7: bool,
     t2_l: u8,
     t2_c: u16,
     t2_trigger_interrupt: bool,
+    t2_load: bool,
+    // Last PB6 level, to detect its negative transitions in pulse counting mode
+    pb6: bool,
+    sr: u8,
+    // Number of bits shifted since the last SR read or write
+    sr_bit_count: u8,
+    sr_is_running: bool,
+    // Cycles left before the next CB1 transition, when the VIA generates the shift clock
+    sr_half_period: u16,
     acr: u8,
     pcr: u8,
     ifr: u8,
@@ -92,12 +131,23 @@ impl W65C22S {
             cb1: false, // Initial value unknown, to test
             ca1: false, // Initial value unknown, to test
             ca2: false, // Initial value unknown, to test
+            ca2_pulse: false,
+            cb2_pulse: false,
             t1_l: 0xbaaa, // This one is weird, the value didn't change on 5 different occasions, to try again
             t1_c: rand::random(), // Test what's in there multiple times in a row and see if it changes
-            t1_is_running: false,
+            t1_trigger_interrupt: false,
+            t1_load: false,
+            t1_reload: false,
+            t1_pb7: true,
             t2_l: 0x00, // Actually unknown, to test by starting the timer and reading the low byte
             t2_c: rand::random(), // Same as t1_c
             t2_trigger_interrupt: false,
+            t2_load: false,
+            pb6: false,
+            sr: 0x00, // Initial value unknown, to test
+            sr_bit_count: 0,
+            sr_is_running: false,
+            sr_half_period: 0,
             acr: 0x00,
             pcr: 0x00,
             ifr: 0x00,
@@ -105,47 +155,356 @@ impl W65C22S {
         }
     }
 
+    /// Data Direction Register A, for systems that need to know which PA pins are outputs.
+    pub fn get_ddra(&self) -> u8 {
+        self.ddra
+    }
+
+    /// Data Direction Register B, the PB pins read from ORB instead of the port.
+    pub fn get_ddrb(&self) -> u8 {
+        self.ddrb
+    }
+
+    /// PB pins driven by the VIA: the outputs set in DDRB, and PB7 when T1 drives it.
+    pub fn get_pb_outputs(&self) -> u8 {
+        self.ddrb | (self.acr & 0b1000_0000)
+    }
+
     /// To call on PHI2 falling edge, *before* calling `read` or `write`
     pub fn clock_pulse<S: ViaSystem>(&mut self, via_system: &mut S) {
-        // Input latching
-        self.irb = via_system.read_port_b(self);
-        self.ira = via_system.read_port_a(self);
+        self.control_lines_pulse(via_system);
 
         // T1 operation
-        if self.t1_is_running {
-            self.t1_c -= 1;
-
-            if self.t1_c == 0 {
-                self.change_interrupt_flag(via_system, Some(6), true);
-                
-                // Restart the timer if in free-run mode
-                if self.acr & 0b0100_0000 != 0 {
-                    self.t1_c = self.t1_l;
-                } else {
-                    self.t1_is_running = false;
+        // The datasheet timings are N+1.5 cycles from the T1C_H write to the interrupt (one-shot mode), 
+        // and N+2 cycles between interrupts (free-run mode). The counter goes through 0xffff 
+        // before being reloaded, and the interrupt flag is set in the middle of that cycle:
+        // it can be seen by the next read, N+2 cycles after the write.
+        if self.t1_load {
+            self.t1_load = false;
+        } else if self.t1_reload {
+            self.t1_reload = false;
+            self.t1_c = self.t1_l;
+        } else {
+            self.t1_c = self.t1_c.wrapping_sub(1);
+
+            if self.t1_c == 0xffff {
+                let free_run = self.acr & 0b0100_0000 != 0;
+
+                if free_run || self.t1_trigger_interrupt {
+                    self.change_interrupt_flag(via_system, Some(6), true);
+                    self.t1_trigger_interrupt = false;
+
+                    // PB7 is inverted on each timeout in free-run mode, 
+                    // and goes back high at the end of the one-shot pulse
+                    self.t1_pb7 = if free_run { !self.t1_pb7 } else { true };
+                    if self.acr & 0b1000_0000 != 0 {
+                        via_system.write_port_b(self, 7, self.t1_pb7);
+                    }
                 }
 
-                // TEST WHAT THE "SINGLE NEGATIVE PULSE" EXACTLY IS
-                // ACTUALLY, THOROUGHLY TEST THE EFFECT OF THE TIMER ON PB7 (WHAT HAPPENS WHEN PB7 IS AN INPUT?)
-                // ALSO TEST WHAT'S IN ORB AFTER THAT
-                if self.acr & 0b1000_0000 != 0 {
-                    // Invert PB7
-                    self.orb ^= 0b1000_0000;
-                    via_system.write_port_b(self, 7, self.orb & 0b1000_0000 != 0);
-                }
+                // In one-shot mode, the counter keeps decrementing from 0xffff
+                self.t1_reload = free_run;
             }
         }
 
         // T2 operation
-        if self.acr & 0b0010_0000 == 0 || self.irb & 0b0100_0000 == 0 {
+        // In pulse counting mode (ACR5 = 1), T2 only counts the negative transitions of PB6
+        let t2_decrement = if self.acr & 0b0010_0000 == 0 {
+            true
+        } else {
+            let pb6 = self.read_pb_pins(via_system) & 0b0100_0000 != 0;
+            let negative_edge = self.pb6 && !pb6;
+            self.pb6 = pb6;
+            negative_edge
+        };
+
+        if self.t2_load {
+            self.t2_load = false;
+        } else if t2_decrement {
+            // "The counter continues to decrement" after the interrupt
             self.t2_c = self.t2_c.wrapping_sub(1);
 
-            if self.t2_c == 0 && self.t2_trigger_interrupt {
+            // As for T1, a one-shot timeout happens when the counter goes through 0xffff (N+1.5 cycles).
+            // In pulse counting mode, IFR5 is set as soon as the counter reaches zero.
+            let timeout = match self.acr & 0b0010_0000 {
+                0 => self.t2_c == 0xffff,
+                _ => self.t2_c == 0,
+            };
+
+            if timeout && self.t2_trigger_interrupt {
                 self.change_interrupt_flag(via_system, Some(5), true);
 
                 self.t2_trigger_interrupt = false;
             }
         }
+
+        self.shift_register_pulse(via_system);
+    }
+
+    // Electrical levels on the PB pins: ORB on the outputs, whatever drives the inputs otherwise
+    fn read_pb_pins<S: ViaSystem>(&mut self, via_system: &mut S) -> u8 {
+        let pins = (self.orb & self.ddrb) | (via_system.read_port_b(self) & !self.ddrb);
+        self.with_t1_pb7(pins)
+    }
+
+    // When ACR7 = 1, PB7 is driven by T1 instead of ORB
+    fn with_t1_pb7(&self, data: u8) -> u8 {
+        if self.acr & 0b1000_0000 != 0 {
+            (data & 0b0111_1111) | ((self.t1_pb7 as u8) << 7)
+        } else {
+            data
+        }
+    }
+
+    // DDRB bits of the PB pins driven by ORB
+    fn orb_outputs(&self) -> u8 {
+        if self.acr & 0b1000_0000 != 0 {
+            self.ddrb & 0b0111_1111
+        } else {
+            self.ddrb
+        }
+    }
+
+    // Called every cycle, ends pulse outputs and detects the active transitions of the input control lines
+    fn control_lines_pulse<S: ViaSystem>(&mut self, via_system: &mut S) {
+        // A pulse output only lasts one cycle
+        if self.ca2_pulse {
+            self.ca2_pulse = false;
+            self.ca2 = true;
+            via_system.write_ca2(self, true);
+        }
+        if self.cb2_pulse {
+            self.cb2_pulse = false;
+            self.cb2 = true;
+            via_system.write_cb2(self, true);
+        }
+
+        // CA1
+        let ca1 = via_system.read_ca1(self);
+        if ca1 != self.ca1 {
+            self.ca1 = ca1;
+
+            // PCR0 = 0: negative active edge, PCR0 = 1: positive active edge
+            if ca1 == (self.pcr & 0b0000_0001 != 0) {
+                self.change_interrupt_flag(via_system, Some(1), true);
+
+                // Input latching: "IRA will reflect the levels on the PA lines
+                // at the time the CA1 interrupt flag is set"
+                if self.acr & 0b0000_0001 != 0 {
+                    self.ira = via_system.read_port_a(self);
+                }
+
+                // Handshake mode: "CA2 set high by an active transition on CA1"
+                if self.ca2_mode() == 0b100 && !self.ca2 {
+                    self.ca2 = true;
+                    via_system.write_ca2(self, true);
+                }
+            }
+        }
+
+        // CA2, when it is an input
+        if self.ca2_mode() & 0b100 == 0 {
+            let ca2 = via_system.read_ca2(self);
+            if ca2 != self.ca2 {
+                self.ca2 = ca2;
+
+                // PCR2 = 0: negative active edge, PCR2 = 1: positive active edge
+                if ca2 == (self.pcr & 0b0000_0100 != 0) {
+                    self.change_interrupt_flag(via_system, Some(0), true);
+                }
+            }
+        }
+
+        // CB1, unless the shift register is generating its own clock on it
+        if let 0b000 | 0b011 | 0b111 = self.sr_mode() {
+            let cb1 = via_system.read_cb1(self);
+            if cb1 != self.cb1 {
+                self.cb1 = cb1;
+
+                // PCR4 = 0: negative active edge, PCR4 = 1: positive active edge
+                if cb1 == (self.pcr & 0b0001_0000 != 0) {
+                    self.change_interrupt_flag(via_system, Some(4), true);
+
+                    // Input latching: IRB holds the levels on the PB input lines 
+                    // at the time the CB1 interrupt flag is set
+                    if self.acr & 0b0000_0010 != 0 {
+                        self.irb = via_system.read_port_b(self);
+                    }
+
+                    // Handshake mode: "CB2 set high by an active transition on CB1"
+                    if self.sr_mode() == 0b000 && self.cb2_mode() == 0b100 && !self.cb2 {
+                        self.cb2 = true;
+                        via_system.write_cb2(self, true);
+                    }
+                }
+
+                // External shift register clock
+                if self.sr_mode() != 0b000 {
+                    self.shift_clock_edge(via_system, cb1);
+                }
+            }
+        }
+
+        // CB2, when it is an input and the shift register doesn't use it
+        if self.sr_mode() == 0b000 && self.cb2_mode() & 0b100 == 0 {
+            let cb2 = via_system.read_cb2(self);
+            if cb2 != self.cb2 {
+                self.cb2 = cb2;
+
+                // PCR6 = 0: negative active edge, PCR6 = 1: positive active edge
+                if cb2 == (self.pcr & 0b0100_0000 != 0) {
+                    self.change_interrupt_flag(via_system, Some(3), true);
+                }
+            }
+        }
+    }
+
+    // CA2 control bits of the PCR (PCR3-1)
+    fn ca2_mode(&self) -> u8 {
+        (self.pcr >> 1) & 0b111
+    }
+
+    // CB2 control bits of the PCR (PCR7-5)
+    fn cb2_mode(&self) -> u8 {
+        (self.pcr >> 5) & 0b111
+    }
+
+    // Any read or write of ORA clears IFR1, and IFR0 unless CA2 is an independent interrupt input.
+    // In handshake or pulse output mode, it also sets CA2 low.
+    fn port_a_handshake<S: ViaSystem>(&mut self, via_system: &mut S) {
+        self.change_interrupt_flag(via_system, Some(1), false);
+        if let 0b001 | 0b011 = self.ca2_mode() {} else {
+            self.change_interrupt_flag(via_system, Some(0), false);
+        }
+
+        if let 0b100 | 0b101 = self.ca2_mode() {
+            self.ca2 = false;
+            self.ca2_pulse = self.ca2_mode() == 0b101;
+            via_system.write_ca2(self, false);
+        }
+    }
+
+    // Any read or write of ORB clears IFR4, and IFR3 unless CB2 is an independent interrupt input.
+    // Unlike port A, only a write sets CB2 low in handshake or pulse output mode.
+    fn port_b_handshake<S: ViaSystem>(&mut self, via_system: &mut S, is_write: bool) {
+        self.change_interrupt_flag(via_system, Some(4), false);
+        if let 0b001 | 0b011 = self.cb2_mode() {} else {
+            self.change_interrupt_flag(via_system, Some(3), false);
+        }
+
+        if is_write && self.sr_mode() == 0b000 {
+            if let 0b100 | 0b101 = self.cb2_mode() {
+                self.cb2 = false;
+                self.cb2_pulse = self.cb2_mode() == 0b101;
+                via_system.write_cb2(self, false);
+            }
+        }
+    }
+
+    // Shift register control bits of the ACR (ACR4-2)
+    fn sr_mode(&self) -> u8 {
+        (self.acr >> 2) & 0b111
+    }
+
+    fn sr_shifts_out(&self) -> bool {
+        self.sr_mode() & 0b100 != 0
+    }
+
+    // Called every cycle, runs the shift register according to the mode set in ACR4-2
+    fn shift_register_pulse<S: ViaSystem>(&mut self, via_system: &mut S) {
+        match self.sr_mode() {
+            // Shift register disabled
+            0b000 => {},
+            // Under control of T2 (including free-running) or PHI2: the VIA outputs the shift clock on CB1
+            0b001 | 0b010 | 0b100 | 0b101 | 0b110 if self.sr_is_running => {
+                if self.sr_half_period > 1 {
+                    self.sr_half_period -= 1;
+                } else {
+                    // "The time between transitions of this output clock is a function of 
+                    // the system clock period and the contents of the low order T2 latch (N)": N+2 cycles.
+                    // Under PHI2 control, CB1 changes on every cycle.
+                    self.sr_half_period = match self.sr_mode() {
+                        0b010 | 0b110 => 1,
+                        _ => self.t2_l as u16 + 2,
+                    };
+
+                    self.cb1 = !self.cb1;
+                    via_system.write_cb1(self, self.cb1);
+                    self.shift_clock_edge(via_system, self.cb1);
+                }
+            },
+            // Stopped, or under control of an external clock on CB1, handled with the other control lines
+            _ => {},
+        }
+    }
+
+    // Shift data in or out on a transition of the shift clock (CB1)
+    fn shift_clock_edge<S: ViaSystem>(&mut self, via_system: &mut S, cb1: bool) {
+        match (self.sr_shifts_out(), cb1) {
+            // "Data is shifted out on the trailing edge of CB1", most significant bit first,
+            // and it re-enters the register at the least significant bit
+            (true, false) => {
+                self.sr = self.sr.rotate_left(1);
+                self.cb2 = self.sr & 0b0000_0001 != 0;
+                via_system.write_cb2(self, self.cb2);
+            },
+            // The bit is over on the leading edge of CB1
+            (true, true) => self.count_shifted_bit(via_system),
+            // "Data is shifted first into the low-order bit of SR" on the leading edge of CB1
+            (false, true) => {
+                self.cb2 = via_system.read_cb2(self);
+                self.sr = (self.sr << 1) | self.cb2 as u8;
+                self.count_shifted_bit(via_system);
+            },
+            (false, false) => {},
+        }
+    }
+
+    fn count_shifted_bit<S: ViaSystem>(&mut self, via_system: &mut S) {
+        self.sr_bit_count += 1;
+
+        if self.sr_bit_count == 8 {
+            self.sr_bit_count = 0;
+
+            match self.sr_mode() {
+                // Free-running mode never stops and never sets IFR2
+                0b100 => {},
+                // Under external control, the counter only interrupts the processor every 8 bits
+                0b011 | 0b111 => self.change_interrupt_flag(via_system, Some(2), true),
+                // Otherwise, shifting stops after 8 bits, CB1 staying high
+                _ => {
+                    self.sr_is_running = false;
+                    self.change_interrupt_flag(via_system, Some(2), true);
+                },
+            }
+        }
+    }
+
+    // A read or write of the SR clears IFR2 and starts a new 8-bit shift
+    fn restart_shift_register<S: ViaSystem>(&mut self, via_system: &mut S) {
+        self.change_interrupt_flag(via_system, Some(2), false);
+        self.sr_bit_count = 0;
+
+        match self.sr_mode() {
+            0b000 => {},
+            // In free-running mode, only a write starts the register,
+            // which is handled by the SR write itself
+            0b100 => {},
+            0b011 | 0b111 => self.sr_is_running = true,
+            // The first CB1 transition is one period away
+            mode => {
+                self.sr_is_running = true;
+                // A restart in the middle of a byte brings the shift clock back high
+                if !self.cb1 {
+                    self.cb1 = true;
+                    via_system.write_cb1(self, self.cb1);
+                }
+                self.sr_half_period = match mode {
+                    0b010 | 0b110 => 1,
+                    _ => self.t2_l as u16 + 2,
+                };
+            },
+        }
     }
 
     /// Ask the VIA for data at the specified register.
@@ -153,17 +512,29 @@ impl W65C22S {
     /// Note: this function will panic if the upper 4 bits of register_select are non-zero.
     pub fn read<S: ViaSystem>(&mut self, via_system: &mut S, register_select: u8) -> u8 {
         match register_select {
-            PORTB => if self.acr & 0b0000_0010 == 0 { // If input latching is disabled
-                // Read PB when DDRB = 0 (input), read ORB otherwise
-                (self.orb & self.ddrb) | (via_system.read_port_b(self) & !self.ddrb)
-            } else {
-                // Read IRB when DDRB = 0 (input), read ORB otherwise
-                (self.orb & self.ddrb) | (self.irb & !self.ddrb)
+            PORTB => {
+                self.port_b_handshake(via_system, false);
+
+                let data = if self.acr & 0b0000_0010 == 0 { // If input latching is disabled
+                    // Read PB when DDRB = 0 (input), read ORB otherwise
+                    (self.orb & self.ddrb) | (via_system.read_port_b(self) & !self.ddrb)
+                } else {
+                    // Read IRB when DDRB = 0 (input), read ORB otherwise
+                    (self.orb & self.ddrb) | (self.irb & !self.ddrb)
+                };
+                self.with_t1_pb7(data)
             },
-            PORTA => if self.acr & 0b0000_0001 == 0 { // If input latching is disabled
-                via_system.read_port_a(self)
-            } else {
-                self.ira
+            PORTA | PA_NO_HS => {
+                // Register 0xF is the same as ORA/IRA, without the handshake
+                if register_select == PORTA {
+                    self.port_a_handshake(via_system);
+                }
+
+                if self.acr & 0b0000_0001 == 0 { // If input latching is disabled
+                    via_system.read_port_a(self)
+                } else {
+                    self.ira
+                }
             },
             DDRB => self.ddrb,
             DDRA => self.ddra,
@@ -183,12 +554,17 @@ impl W65C22S {
                 (self.t2_c & 0x00ff) as u8
             },
             T2C_H => ((self.t2_c & 0xff00) >> 8) as u8,
+            SR => {
+                let data = self.sr;
+                self.restart_shift_register(via_system);
+                data
+            },
             ACR => self.acr,
             PCR => self.pcr,
             IFR => self.ifr,
+            // "If the IER is read, bit 7 will always be a logic 1"
             IER => self.ier | 0b1000_0000,
-            0x10..=0xff => panic!("Illegal Register Select value! (Expected: 0 <= RS <= 15)"),
-            _ => todo!(),
+            _ => panic!("Illegal Register Select value! (Expected: 0 <= RS <= 15)"),
         }
     }
 
@@ -198,15 +574,23 @@ impl W65C22S {
     pub fn write<S: ViaSystem>(&mut self, via_system: &mut S, register_select: u8, data: u8) {
         match register_select {
             PORTB => {
+                self.port_b_handshake(via_system, true);
+
                 self.orb = data;
                 // Only change the bit of PB when DDRB = 1 (output)
+                let outputs = self.orb_outputs();
                 for i in 0..=7 {
-                    if (self.ddrb >> i) & 1 == 1 {
+                    if (outputs >> i) & 1 == 1 {
                         via_system.write_port_b(self, i, (data >> i) & 1 == 1);
                     }
                 }
             },
-            PORTA => {
+            PORTA | PA_NO_HS => {
+                // Register 0xF is the same as ORA, without the handshake
+                if register_select == PORTA {
+                    self.port_a_handshake(via_system);
+                }
+
                 self.ora = data;
                 // Only change the bit of PA when DDRA = 1 (output)
                 for i in 0..=7 {
@@ -217,12 +601,14 @@ impl W65C22S {
             },
             DDRB => {
                 // Update PB when a DDRB bit goes from 0 (input) to 1 (output)
+                let outputs = self.orb_outputs();
+                self.ddrb = data;
+                let new_outputs = self.orb_outputs();
                 for i in 0..=7 {
-                    if (self.ddrb >> i) & 1 == 0 && (data >> i) & 1 == 1 {
+                    if (outputs >> i) & 1 == 0 && (new_outputs >> i) & 1 == 1 {
                         via_system.write_port_b(self, i, (self.orb >> i) & 1 == 1);
                     }
                 }
-                self.ddrb = data;
             },
             DDRA => {
                 // Update PA when a DDRA bit goes from 0 (input) to 1 (output)
@@ -239,9 +625,17 @@ impl W65C22S {
                 // "Also, both high and low order latches are transferred 
                 // into T1 counter and this initiates countdown"
                 self.t1_c = self.t1_l;
-                self.t1_is_running = true;
+                self.t1_load = true;
+                self.t1_reload = false;
+                self.t1_trigger_interrupt = true;
                 // "T1 interrupt flag IFR6 is reset"
                 self.change_interrupt_flag(via_system, Some(6), false);
+
+                // PB7 goes low for the duration of the count
+                self.t1_pb7 = false;
+                if self.acr & 0b1000_0000 != 0 {
+                    via_system.write_port_b(self, 7, false);
+                }
             },
             T1C_L | T1L_L => {
                 // "8 bits loaded into T1 low order latches"
@@ -261,21 +655,60 @@ impl W65C22S {
                 // "8 bits loaded into T2 high order counter.
                 // Also, low order latches are transferred to low order counter"
                 self.t2_c = ((data as u16) << 8) | self.t2_l as u16;
+                // Pulses are counted right away, but a timed countdown only starts on the next cycle
+                self.t2_load = self.acr & 0b0010_0000 == 0;
                 // "IFR5 is reset"
                 self.change_interrupt_flag(via_system, Some(5), false);
                 self.t2_trigger_interrupt = true;
             },
-            ACR => self.acr = data,
+            SR => {
+                self.sr = data;
+                self.restart_shift_register(via_system);
+
+                if self.sr_mode() == 0b100 && !self.sr_is_running {
+                    self.sr_is_running = true;
+                    self.cb1 = true;
+                    self.sr_half_period = self.t2_l as u16 + 2;
+                }
+            },
+            ACR => {
+                // Disabling the shift register stops it
+                if data & 0b0001_1100 == 0 {
+                    self.sr_is_running = false;
+                }
+                // Entering pulse counting mode, the current PB6 level isn't a transition
+                if self.acr & 0b0010_0000 == 0 && data & 0b0010_0000 != 0 {
+                    self.pb6 = self.read_pb_pins(via_system) & 0b0100_0000 != 0;
+                }
+
+                let pb7_was_t1 = self.acr & 0b1000_0000 != 0;
+                self.acr = data;
+
+                // PB7 switches between T1 and ORB
+                match (pb7_was_t1, data & 0b1000_0000 != 0) {
+                    (false, true) => via_system.write_port_b(self, 7, self.t1_pb7),
+                    (true, false) if self.ddrb & 0b1000_0000 != 0 => {
+                        via_system.write_port_b(self, 7, self.orb & 0b1000_0000 != 0);
+                    },
+                    _ => {},
+                }
+            },
             PCR => {
                 self.pcr = data;
                 
-                // If CB2 is in output mode
-                if data >> 7 == 1 {
-                    if data >> 5 == 0b110 {
-                        via_system.write_cb2(self, false);
-                    } else {
-                        via_system.write_cb2(self, true);
-                    }
+                // If CA2 is in output mode, it is high unless set to "low output".
+                // In handshake and pulse modes, it is high until the next ORA access.
+                if self.ca2_mode() & 0b100 != 0 {
+                    self.ca2 = self.ca2_mode() != 0b110;
+                    self.ca2_pulse = false;
+                    via_system.write_ca2(self, self.ca2);
+                }
+
+                // Same for CB2, unless the shift register is using it
+                if self.cb2_mode() & 0b100 != 0 && self.sr_mode() == 0b000 {
+                    self.cb2 = self.cb2_mode() != 0b110;
+                    self.cb2_pulse = false;
+                    via_system.write_cb2(self, self.cb2);
                 }
             },
             IFR => {
@@ -283,14 +716,14 @@ impl W65C22S {
                 self.change_interrupt_flag(via_system, None, true);
             },
             IER => {
+                // Bit 7 selects whether the other 1 bits set or clear their IER bit
                 self.ier = match data & 0b1000_0000 {
                     0 => self.ier & !data,
                     _ => self.ier | data,
-                };
+                } & 0b0111_1111;
                 self.change_interrupt_flag(via_system, None, true);
             },
-            0x10..=0xff => panic!("Illegal Register Select value! (Expected: 0 <= RS <= 15)"),
-            _ => todo!(),
+            _ => panic!("Illegal Register Select value! (Expected: 0 <= RS <= 15)"),
         }
     }
 
@@ -332,7 +765,9 @@ mod tests {
     
     use std::sync::mpsc::{self, Receiver};
     use crate::LogMessage;
-    use crate::system::PhysSystem;
+    use crate::system::{PhysSystem, ViaSlot, Wiring};
+    use crate::system::lcd::{LCD, Geometry, CharRom};
+    use w65c02s::{System, W65C02S};
 
     fn create_test_sys() -> (PhysSystem, Receiver<LogMessage>) {
         let (tx_log_msgs, rx_log_msgs) = mpsc::channel();
@@ -342,58 +777,92 @@ mod tests {
         }, rx_log_msgs)
     }
 
+    // Lets the tests drive the VIA inputs and record its outputs
+    #[derive(Default)]
+    struct TestViaSystem {
+        pa: u8,
+        pb: u8,
+        ca1_in: bool,
+        ca2_in: bool,
+        cb1_in: bool,
+        cb2_in: bool,
+        ca2_out: Vec<bool>,
+        cb1_out: Vec<bool>,
+        cb2_out: Vec<bool>,
+        irq: bool,
+    }
+
+    impl ViaSystem for TestViaSystem {
+        fn read_port_b(&mut self, _via: &mut W65C22S) -> u8 { self.pb }
+        fn read_port_a(&mut self, _via: &mut W65C22S) -> u8 { self.pa }
+        fn write_port_b(&mut self, _via: &mut W65C22S, bit: u8, level: bool) {
+            self.pb = (self.pb & !(1 << bit)) | ((level as u8) << bit);
+        }
+        fn write_port_a(&mut self, _via: &mut W65C22S, bit: u8, level: bool) {
+            self.pa = (self.pa & !(1 << bit)) | ((level as u8) << bit);
+        }
+        fn write_ca2(&mut self, _via: &mut W65C22S, level: bool) { self.ca2_out.push(level) }
+        fn write_cb1(&mut self, _via: &mut W65C22S, level: bool) { self.cb1_out.push(level) }
+        fn write_cb2(&mut self, _via: &mut W65C22S, level: bool) { self.cb2_out.push(level) }
+        fn read_ca1(&mut self, _via: &mut W65C22S) -> bool { self.ca1_in }
+        fn read_ca2(&mut self, _via: &mut W65C22S) -> bool { self.ca2_in }
+        fn read_cb1(&mut self, _via: &mut W65C22S) -> bool { self.cb1_in }
+        fn read_cb2(&mut self, _via: &mut W65C22S) -> bool { self.cb2_in }
+        fn update_irq(&mut self, _via: &mut W65C22S, irq: bool) { self.irq = irq }
+    }
+
     #[test]
     fn simple_write_pb() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
 
         via.write(&mut sys, DDRB, 0b1111_1111);
         via.write(&mut sys, PORTB, 0x42);
 
-        assert_eq!(0x42, sys.via_pb);
+        assert_eq!(0x42, sys.vias[0].pb);
     }
 
     #[test]
     fn complex_write_pb() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
         
         via.write(&mut sys, PORTB, 0b1100_0011);
         
         via.write(&mut sys, DDRB, 0b0110_1001);
         via.write(&mut sys, PORTB, 0b1010_0111);
         
-        assert_eq!(0b0010_0001, sys.via_pb & 0b0110_1001);
+        assert_eq!(0b0010_0001, sys.vias[0].pb & 0b0110_1001);
     }
 
     #[test]
     fn change_ddrb() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
         
         via.write(&mut sys, DDRB, 0xff);
         via.write(&mut sys, PORTB, 0xc3);
 
-        assert_eq!(0xc3, sys.via_pb);
+        assert_eq!(0xc3, sys.vias[0].pb);
 
         via.write(&mut sys, DDRB, 0x00);
         via.write(&mut sys, PORTB, 0x42);
 
-        assert_eq!(0xc3, sys.via_pb);
+        assert_eq!(0xc3, sys.vias[0].pb);
 
         via.write(&mut sys, DDRB, 0x0f);
 
-        assert_eq!(0xc2, sys.via_pb);
+        assert_eq!(0xc2, sys.vias[0].pb);
 
         via.write(&mut sys, DDRB, 0xff);
 
-        assert_eq!(0x42, sys.via_pb);
+        assert_eq!(0x42, sys.vias[0].pb);
     }
 
     #[test]
     fn t1_one_shot() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
         
         // PB7 disabled, one-shot mode
         via.write(&mut sys, ACR, 0b0000_0011);
@@ -408,21 +877,32 @@ mod tests {
         via.write(&mut sys, T1C_L, 0x02);
         via.write(&mut sys, T1C_H, 0x00);
 
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
+        // The counter is loaded during the cycle following the write, then goes 2, 1, 0, 0xffff
+        for expected in [0x02, 0x02, 0x01, 0x00] {
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+            assert_eq!(expected, via.t1_c);
+            via.clock_pulse(&mut sys);
+        }
+        // N+1.5 cycles after the write
         assert_eq!(0b1100_0000, via.read(&mut sys, IFR), "IFR6 should be set");
+        assert_eq!(0xff, via.read(&mut sys, T1C_H));
+        assert_eq!(0xff, via.read(&mut sys, T1C_L));
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Reading T1C_L should reset IFR6");
         
+        // The counter keeps decrementing, but doesn't set IFR6 again
         via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, T1C_L), "Timer shouldn't be running anymore");
-        assert_eq!(0x00, via.read(&mut sys, T1C_H), "Timer shouldn't be running anymore");
+        assert_eq!(0xfe, via.read(&mut sys, T1C_L), "Timer should still be running");
+        assert_eq!(0xff, via.read(&mut sys, T1C_H), "Timer should still be running");
+        for _i in 0..65_536 {
+            via.clock_pulse(&mut sys);
+        }
+        assert_eq!(0x00, via.read(&mut sys, IFR), "A one-shot timer only interrupts once");
     }
 
     #[test]
     fn t1_os_long_timer() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
         
         // PB7 disabled, one-shot mode
         via.write(&mut sys, ACR, 0b0000_0011);
@@ -432,21 +912,21 @@ mod tests {
         via.write(&mut sys, T1C_L, (500u16 & 0x00ff) as u8);
         via.write(&mut sys, T1C_H, ((500u16 & 0xff00) >> 8) as u8);
         
-        for _i in 0..500 {
+        for _i in 0..502 {
             assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
             via.clock_pulse(&mut sys);
         }
         assert_eq!(0b1100_0000, via.read(&mut sys, IFR), "IFR6 should be set");
         
         via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, T1C_L), "Timer shouldn't be running anymore");
-        assert_eq!(0x00, via.read(&mut sys, T1C_H), "Timer shouldn't be running anymore");
+        assert_eq!(0xfe, via.read(&mut sys, T1C_L), "Timer should still be running");
+        assert_eq!(0xff, via.read(&mut sys, T1C_H), "Timer should still be running");
     }
         
     #[test]
     fn t1_freerun() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
 
         // PB7 disabled, freerun mode
         via.write(&mut sys, ACR, 0b0100_0011);
@@ -456,26 +936,87 @@ mod tests {
         via.write(&mut sys, T1C_L, 0x02);
         via.write(&mut sys, T1C_H, 0x00);
 
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
+        for _i in 0..4 {
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+            via.clock_pulse(&mut sys);
+        }
         assert_eq!(0b1100_0000, via.read(&mut sys, IFR), "IFR6 should be set");
-
-        via.clock_pulse(&mut sys);
+        assert_eq!(0xffff, via.t1_c, "The counter goes through 0xffff before the reload");
 
         // Clear IFR6
         via.write(&mut sys, IFR, 0b0100_0000);
         assert_eq!(0x00, via.read(&mut sys, IFR), "Interrupt flags should have been reset");
 
+        // N+2 cycles between interrupts: reload, 1, 0, 0xffff
+        for expected in [0x02, 0x01, 0x00] {
+            via.clock_pulse(&mut sys);
+            assert_eq!(expected, via.t1_c);
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+        }
         via.clock_pulse(&mut sys);
         assert_eq!(0b1100_0000, via.read(&mut sys, IFR), "IFR6 should be set");
     }
 
+    #[test]
+    fn t1_pb7_one_shot() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // PB7 enabled, one-shot mode
+        via.write(&mut sys, ACR, 0b1000_0000);
+        assert_eq!(0b1000_0000, sys.pb, "PB7 should be high until the timer starts");
+
+        via.write(&mut sys, T1C_L, 0x03);
+        via.write(&mut sys, T1C_H, 0x00);
+        assert_eq!(0b0000_0000, sys.pb, "PB7 should go low when T1C_H is written");
+
+        // The pulse lasts N+1.5 cycles
+        for _i in 0..4 {
+            via.clock_pulse(&mut sys);
+            assert_eq!(0b0000_0000, sys.pb, "PB7 should still be low");
+        }
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b1000_0000, sys.pb, "PB7 should be back high");
+
+        // ORB doesn't drive PB7 while the timer does
+        via.write(&mut sys, DDRB, 0xff);
+        via.write(&mut sys, PORTB, 0x00);
+        assert_eq!(0b1000_0000, sys.pb);
+        assert_eq!(0b1000_0000, via.read(&mut sys, PORTB));
+
+        for _i in 0..65_536 {
+            via.clock_pulse(&mut sys);
+        }
+        assert_eq!(0b1000_0000, sys.pb, "There should be a single pulse");
+
+        // Disabling PB7 gives it back to ORB
+        via.write(&mut sys, ACR, 0b0000_0000);
+        assert_eq!(0b0000_0000, sys.pb);
+    }
+
+    #[test]
+    fn t1_pb7_freerun() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // PB7 enabled, freerun mode
+        via.write(&mut sys, ACR, 0b1100_0000);
+        via.write(&mut sys, T1C_L, 0x01);
+        via.write(&mut sys, T1C_H, 0x00);
+
+        // Square wave with a half period of N+2 cycles
+        let mut levels = vec![];
+        for _i in 0..18 {
+            via.clock_pulse(&mut sys);
+            levels.push(sys.pb >> 7);
+        }
+        assert_eq!(vec![0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 1, 0], levels);
+    }
+
     #[test]
     fn t2_one_shot() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
         
         // One-shot mode
         via.write(&mut sys, ACR, 0b0000_0011);
@@ -485,28 +1026,29 @@ mod tests {
         via.write(&mut sys, T2C_L, 0x02);
         via.write(&mut sys, T2C_H, 0x00);
 
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
+        // Same N+1.5 cycles timing as T1
+        for _i in 0..4 {
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+            via.clock_pulse(&mut sys);
+        }
         assert_eq!(0b1010_0000, via.read(&mut sys, IFR), "IFR5 should be set");
         
         via.clock_pulse(&mut sys);
         assert_eq!(0b1010_0000, via.read(&mut sys, IFR), "IFR5 should still be set");
         
-        assert_eq!(0xff, via.read(&mut sys, T2C_L), "Timer should still be running");
         assert_eq!(0xff, via.read(&mut sys, T2C_H), "Timer should still be running");
+        assert_eq!(0xfe, via.read(&mut sys, T2C_L), "Timer should still be running");
         assert_eq!(0b0000_0000, via.read(&mut sys, IFR), "IFR5 should be reset");
         
         via.clock_pulse(&mut sys);
-        assert_eq!(0xfe, via.read(&mut sys, T2C_L), "Timer should still be running");
+        assert_eq!(0xfd, via.read(&mut sys, T2C_L), "Timer should still be running");
         assert_eq!(0xff, via.read(&mut sys, T2C_H), "Timer should still be running");
     }
 
     #[test]
     fn t2_full_cycle() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
         
         // One-shot mode
         via.write(&mut sys, ACR, 0b0000_0011);
@@ -516,10 +1058,10 @@ mod tests {
         via.write(&mut sys, T2C_L, 0x02);
         via.write(&mut sys, T2C_H, 0x00);
 
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        via.clock_pulse(&mut sys);
+        for _i in 0..4 {
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+            via.clock_pulse(&mut sys);
+        }
         assert_eq!(0b1010_0000, via.read(&mut sys, IFR), "IFR5 should be set");
         
         // Clear IFR5
@@ -530,18 +1072,16 @@ mod tests {
             assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set");
             via.clock_pulse(&mut sys);
         }
-        assert_eq!(0xfd, via.read(&mut sys, T2C_L), "Timer should still be running");
+        assert_eq!(0xfc, via.read(&mut sys, T2C_L), "Timer should still be running");
         assert_eq!(0xff, via.read(&mut sys, T2C_H), "Timer should still be running");
     }
 
     #[test]
     fn t2_pulse_count() {
-        todo!("Fix pulse count test");
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
 
-        let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
-
-        via.write(&mut sys, PORTB, 0xff);
+        sys.pb = 0b0100_0000;
 
         // Pulse counting mode
         via.write(&mut sys, ACR, 0b0010_0011);
@@ -551,36 +1091,73 @@ mod tests {
         via.write(&mut sys, T2C_L, 0x03);
         via.write(&mut sys, T2C_H, 0x00);
 
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+        // PB6 staying high or low doesn't count
         via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
         via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+        assert_eq!(0x03, via.read(&mut sys, T2C_L), "No negative transition yet");
         
-        via.orb = via.irb & 0b1011_1111; //ALLOW WRITE OF A SINGLE BIT...
-        via.clock_pulse(&mut sys);
-        via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
-        
-        via.orb = via.irb | 0b0100_0000;
-        via.clock_pulse(&mut sys);
-        assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+        for expected in [0x02, 0x01] {
+            sys.pb = 0b0000_0000;
+            via.clock_pulse(&mut sys);
+            via.clock_pulse(&mut sys);
+            assert_eq!(expected, via.read(&mut sys, T2C_L), "Each negative transition should count once");
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+
+            sys.pb = 0b0100_0000;
+            via.clock_pulse(&mut sys);
+            assert_eq!(expected, via.read(&mut sys, T2C_L), "Positive transitions don't count");
+        }
 
-        via.orb = via.irb & 0b1011_1111;
+        sys.pb = 0b0000_0000;
         via.clock_pulse(&mut sys);
         assert_eq!(0b1010_0000, via.read(&mut sys, IFR), "IFR5 should be set");
+        assert_eq!(0x00, via.read(&mut sys, T2C_L));
+        assert_eq!(0b0000_0000, via.read(&mut sys, IFR), "Reading T2C_L should reset IFR5");
 
+        // The counter rolls over, without setting IFR5 again
+        for expected in [0xffff, 0xfffe] {
+            sys.pb = 0b0100_0000;
+            via.clock_pulse(&mut sys);
+            sys.pb = 0b0000_0000;
+            via.clock_pulse(&mut sys);
+            assert_eq!((expected >> 8) as u8, via.read(&mut sys, T2C_H));
+            assert_eq!(expected as u8, via.read(&mut sys, T2C_L));
+        }
+        assert_eq!(0x00, via.read(&mut sys, IFR), "IFR5 shouldn't be set again before T2C_H is rewritten");
+
+        // Rewriting T2C_H allows IFR5 to be set again
+        via.write(&mut sys, T2C_L, 0x01);
+        via.write(&mut sys, T2C_H, 0x00);
+        sys.pb = 0b0100_0000;
         via.clock_pulse(&mut sys);
+        sys.pb = 0b0000_0000;
         via.clock_pulse(&mut sys);
-        assert_eq!(0xfe, via.read(&mut sys, T2C_L), "Timer should still be running");
-        assert_eq!(0xff, via.read(&mut sys, T2C_H), "Timer should still be running");
+        assert_eq!(0b1010_0000, via.read(&mut sys, IFR), "IFR5 should be set");
     }
 
+    #[test]
+    fn t2_pulse_count_pb6_output() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // PB6 is an output: the VIA counts its own pulses, whatever the peripheral does
+        via.write(&mut sys, DDRB, 0b0100_0000);
+        via.write(&mut sys, PORTB, 0b0100_0000);
+        via.write(&mut sys, ACR, 0b0010_0000);
+        via.write(&mut sys, T2C_L, 0x02);
+        via.write(&mut sys, T2C_H, 0x00);
+
+        via.write(&mut sys, PORTB, 0b0000_0000);
+        via.clock_pulse(&mut sys);
+        via.write(&mut sys, PORTB, 0b0100_0000);
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x01, via.read(&mut sys, T2C_L));
+    }
 
     #[test]
     fn interrupts() {
         let (mut sys, _rx_log_msgs) = create_test_sys();
-        let mut via = sys.via;
+        let mut via = sys.vias[0].via;
 
         assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupts should be enabled yet");
         assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
@@ -619,4 +1196,598 @@ mod tests {
         assert_eq!(0b1000_0010, via.read(&mut sys, IER));
         assert_eq!(0b0010_1000, via.read(&mut sys, IFR), "IFR7 should be reset");
     }
+
+    #[test]
+    fn multiple_vias_shared_irq() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        sys.vias.push(ViaSlot::new(0x5000, &sys.prgm_config.wiring, &mut sys.irq_line));
+        let mut cpu = W65C02S::new();
+
+        // T1 one-shot on the second VIA only
+        sys.write(&mut cpu, 0x5000 + IER as u16, 0b1100_0000);
+        sys.write(&mut cpu, 0x5000 + T1C_L as u16, 0x02);
+        sys.write(&mut cpu, 0x5000 + T1C_H as u16, 0x00);
+        assert!(!sys.irq_line.is_asserted());
+        for _ in 0..4 {
+            // Stack reads don't check for garbage
+            sys.read(&mut cpu, 0x0100);
+        }
+        assert!(sys.irq_line.is_asserted());
+        assert_eq!(vec!["VIA $5000"], sys.irq_line.asserting_sources());
+        assert_eq!(0x00, sys.read(&mut cpu, 0x6000 + IFR as u16), "The first VIA shouldn't be affected");
+
+        // Each VIA drives its own ports
+        sys.write(&mut cpu, 0x6000 + DDRA as u16, 0xff);
+        sys.write(&mut cpu, 0x6000 + PORTA as u16, 0x5a);
+        sys.write(&mut cpu, 0x5000 + DDRA as u16, 0xff);
+        sys.write(&mut cpu, 0x5000 + PORTA as u16, 0xa5);
+        assert_eq!(0x5a, sys.vias[0].pa);
+        assert_eq!(0xa5, sys.vias[1].pa);
+
+        sys.read(&mut cpu, 0x5000 + T1C_L as u16);
+        assert!(!sys.irq_line.is_asserted());
+    }
+
+    #[test]
+    fn wired_pins() {
+        let (mut sys, _rx_log_msgs) = create_test_sys();
+        let wiring = Wiring::parse("[6000]\nPA0 pullup\nPA1 pulldown\nCA1 pulldown").unwrap();
+        sys.vias[0].nets = wiring.nets_of(0x6000);
+        let mut via = sys.vias[0].via;
+
+        // PA7 is an output, PA0 and PA1 are inputs held by their resistors
+        via.write(&mut sys, DDRA, 0b1000_0000);
+        via.write(&mut sys, PORTA, 0b1000_0000);
+        assert_eq!(0b1000_0001, via.read(&mut sys, PORTA) & 0b1000_0011);
+        via.write(&mut sys, PORTA, 0b0000_0000);
+        assert_eq!(0b0000_0001, via.read(&mut sys, PORTA) & 0b1000_0011);
+
+        // CA1 is held low, so setting PCR0 makes a positive edge impossible
+        via.write(&mut sys, PCR, 0b0000_0001);
+        for _ in 0..10 {
+            via.clock_pulse(&mut sys);
+        }
+        assert_eq!(0x00, via.read(&mut sys, IFR) & 0b0000_0010);
+    }
+
+    #[test]
+    fn bus_contention() {
+        let (mut sys, rx_log_msgs) = create_test_sys();
+        let (tx_gui_msgs, _rx_gui_msgs) = mpsc::channel();
+        sys.lcd = Some(LCD::new(Geometry::default(), CharRom::a00(), 1_000_000, None, 
+            sys.tx_log_msgs.clone(), tx_gui_msgs));
+        let mut cpu = W65C02S::new();
+        let warnings = || -> Vec<String> {
+            rx_log_msgs.try_iter().filter_map(|msg| match msg {
+                LogMessage::Log(msg) if msg.contains("WARNING") => Some(msg),
+                _ => None,
+            }).collect()
+        };
+
+        // Port B as outputs, then RW and E high: the LCD drives DB4-DB7, wired to PB0-PB3
+        sys.write(&mut cpu, 0x6002, 0xff);
+        sys.write(&mut cpu, 0x6000, 0b0100_0000);
+        sys.write(&mut cpu, 0x6000, 0b0110_0000);
+        sys.read(&mut cpu, 0x0100);
+        sys.read(&mut cpu, 0x0100);
+        let contentions = warnings();
+        assert_eq!(1, contentions.len(), "The contention should only be flagged when it starts");
+        assert!(contentions[0].contains("bus contention on VIA $6000, PB0 ([Via, Lcd]), PB1 ([Via, Lcd]), \
+            PB2 ([Via, Lcd]), PB3 ([Via, Lcd]) driven"));
+
+        // PB0-PB3 as inputs, only driven by the LCD
+        sys.write(&mut cpu, 0x6002, 0xf0);
+        sys.read(&mut cpu, 0x6000);
+        assert_eq!(Vec::<String>::new(), warnings());
+        assert_eq!(0, sys.vias[0].contended_pins);
+
+        // E low, so nothing drives PB0-PB3 anymore
+        sys.write(&mut cpu, 0x6000, 0b0100_0000);
+        sys.read(&mut cpu, 0x6000);
+        let floating = warnings();
+        assert_eq!(1, floating.len());
+        assert!(floating[0].contains("reading floating PB0 PB1 PB2 PB3 of VIA $6000"));
+
+        // Reading them again doesn't flag them again, until the LCD has driven them in between
+        sys.read(&mut cpu, 0x6000);
+        assert_eq!(Vec::<String>::new(), warnings());
+        sys.write(&mut cpu, 0x6000, 0b0110_0000);
+        sys.read(&mut cpu, 0x6000);
+        sys.write(&mut cpu, 0x6000, 0b0100_0000);
+        sys.read(&mut cpu, 0x6000);
+        assert_eq!(1, warnings().len());
+    }
+
+    #[test]
+    fn sr_shift_out_phi2() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // Shift out under control of PHI2
+        via.write(&mut sys, ACR, 0b0001_1000);
+        // Set IER2
+        via.write(&mut sys, IER, 0b1000_0100);
+        via.write(&mut sys, SR, 0b1011_0010);
+
+        for _i in 0..15 {
+            via.clock_pulse(&mut sys);
+            assert_eq!(0x00, via.read(&mut sys, IFR), "No interrupt flags should be set yet");
+        }
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b1000_0100, via.read(&mut sys, IFR), "IFR2 should be set");
+        assert!(sys.irq);
+
+        assert_eq!(vec![true, false, true, true, false, false, true, false], sys.cb2_out,
+            "CB2 should output the SR, most significant bit first");
+        // CB1 goes high when the shift starts, then 16 transitions
+        assert_eq!(17, sys.cb1_out.len());
+        assert_eq!(Some(&true), sys.cb1_out.last(), "CB1 should rest high");
+
+        // Shifting out rotates the register, so it is back to its original value
+        assert_eq!(0b1011_0010, via.read(&mut sys, SR));
+        assert_eq!(0b0000_0000, via.read(&mut sys, IFR), "Reading SR should reset IFR2");
+
+        // Reading SR started another shift
+        for _i in 0..16 {
+            via.clock_pulse(&mut sys);
+        }
+        assert_eq!(0b1000_0100, via.read(&mut sys, IFR), "IFR2 should be set");
+        assert_eq!(33, sys.cb1_out.len());
+
+        // The shift register stopped
+        via.clock_pulse(&mut sys);
+        assert_eq!(33, sys.cb1_out.len());
+
+        // Writing SR while CB1 is low restarts the shift with CB1 high
+        via.write(&mut sys, SR, 0x00);
+        via.clock_pulse(&mut sys);
+        assert_eq!(Some(&false), sys.cb1_out.last());
+        via.write(&mut sys, SR, 0x00);
+        assert_eq!(35, sys.cb1_out.len());
+        assert_eq!(Some(&true), sys.cb1_out.last(), "CB1 should be back high");
+    }
+
+    #[test]
+    fn sr_shift_in_t2() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // Shift in under control of T2, N = 1
+        via.write(&mut sys, ACR, 0b0000_0100);
+        via.write(&mut sys, T2C_L, 0x01);
+        via.write(&mut sys, SR, 0x00);
+
+        // Each CB1 half period lasts N+2 cycles
+        let data = 0b0110_1101u8;
+        for bit in (0..8).rev() {
+            sys.cb2_in = (data >> bit) & 1 == 1;
+            for _i in 0..6 {
+                assert_eq!(0x00, via.read(&mut sys, IFR) & 0b0000_0100, "IFR2 shouldn't be set yet");
+                via.clock_pulse(&mut sys);
+            }
+        }
+        assert_eq!(0b0000_0100, via.read(&mut sys, IFR), "IFR2 should be set");
+        assert_eq!(data, via.read(&mut sys, SR));
+        assert_eq!([vec![true], [false, true].repeat(8)].concat(), sys.cb1_out);
+    }
+
+    #[test]
+    fn sr_shift_in_external() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // Shift in under control of an external clock on CB1
+        via.write(&mut sys, ACR, 0b0000_1100);
+        via.write(&mut sys, SR, 0x00);
+
+        let data = 0b1100_1010u8;
+        for bit in (0..8).rev() {
+            sys.cb1_in = false;
+            via.clock_pulse(&mut sys);
+            sys.cb2_in = (data >> bit) & 1 == 1;
+            sys.cb1_in = true;
+            via.clock_pulse(&mut sys);
+        }
+        // The CB1 clock edges also set IFR4
+        assert_eq!(0b0000_0100, via.read(&mut sys, IFR) & 0b0000_0100, "IFR2 should be set");
+        assert_eq!(data, via.read(&mut sys, SR));
+        assert!(sys.cb1_out.is_empty(), "CB1 is an input");
+
+        // Nothing happens without clock edges
+        via.clock_pulse(&mut sys);
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b0000_0000, via.read(&mut sys, IFR) & 0b0000_0100);
+        assert_eq!(data, via.read(&mut sys, SR));
+    }
+
+    #[test]
+    fn sr_free_running() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // Free-running shift out at the T2 rate, N = 0
+        via.write(&mut sys, ACR, 0b0001_0000);
+        via.write(&mut sys, IER, 0b1000_0100);
+        via.write(&mut sys, T2C_L, 0x00);
+        via.write(&mut sys, SR, 0b1000_0001);
+
+        // 3 bytes worth of bits, each bit lasting 2 * (N+2) cycles
+        for _i in 0..(3 * 8 * 4) {
+            via.clock_pulse(&mut sys);
+        }
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Free-running mode shouldn't set IFR2");
+        assert!(!sys.irq);
+        assert_eq!([true, false, false, false, false, false, false, true].repeat(3), sys.cb2_out);
+
+        // Disabling the shift register stops it
+        via.write(&mut sys, ACR, 0b0000_0000);
+        via.clock_pulse(&mut sys);
+        via.clock_pulse(&mut sys);
+        assert_eq!(24, sys.cb2_out.len());
+    }
+
+    #[test]
+    fn ca1_active_edge() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+        via.write(&mut sys, IER, 0b1000_0010);
+
+        // Negative active edge
+        sys.ca1_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "A positive edge shouldn't set IFR1");
+        sys.ca1_in = false;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b1000_0010, via.read(&mut sys, IFR), "IFR1 should be set");
+        assert!(sys.irq);
+
+        via.read(&mut sys, PORTA);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Reading ORA should clear IFR1");
+        assert!(!sys.irq);
+
+        // Positive active edge
+        via.write(&mut sys, PCR, 0b0000_0001);
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "No edge, no interrupt");
+        sys.ca1_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b1000_0010, via.read(&mut sys, IFR), "IFR1 should be set");
+
+        via.write(&mut sys, PORTA, 0x00);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Writing ORA should clear IFR1");
+    }
+
+    #[test]
+    fn ca2_independent_interrupt() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // CA2 input, positive active edge
+        via.write(&mut sys, PCR, 0b0000_0100);
+        sys.ca2_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b0000_0001, via.read(&mut sys, IFR), "IFR0 should be set");
+        via.read(&mut sys, PORTA);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Reading ORA should clear IFR0");
+
+        // CA2 independent interrupt input, negative active edge
+        via.write(&mut sys, PCR, 0b0000_0010);
+        sys.ca2_in = false;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b0000_0001, via.read(&mut sys, IFR), "IFR0 should be set");
+        via.read(&mut sys, PORTA);
+        via.write(&mut sys, PORTA, 0x00);
+        assert_eq!(0b0000_0001, via.read(&mut sys, IFR), "ORA accesses shouldn't clear IFR0");
+        via.write(&mut sys, IFR, 0b0000_0001);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Writing IFR should clear IFR0");
+    }
+
+    #[test]
+    fn ca2_handshake() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // CA2 handshake output, CA1 negative active edge
+        via.write(&mut sys, PCR, 0b0000_1000);
+        assert_eq!(vec![true], sys.ca2_out);
+        sys.ca1_in = true;
+        via.clock_pulse(&mut sys);
+
+        // Read handshake: "data taken"
+        via.read(&mut sys, PORTA);
+        assert_eq!(vec![true, false], sys.ca2_out);
+        via.clock_pulse(&mut sys);
+        via.clock_pulse(&mut sys);
+        assert_eq!(vec![true, false], sys.ca2_out, "CA2 should stay low until the CA1 active edge");
+
+        // "Data ready"
+        sys.ca1_in = false;
+        via.clock_pulse(&mut sys);
+        assert_eq!(vec![true, false, true], sys.ca2_out);
+        assert_eq!(0b0000_0010, via.read(&mut sys, IFR), "IFR1 should be set");
+
+        // Write handshake
+        via.write(&mut sys, PORTA, 0x42);
+        assert_eq!(vec![true, false, true, false], sys.ca2_out);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Writing ORA should clear IFR1");
+    }
+
+    #[test]
+    fn ca2_cb2_pulse_output() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // CA2 and CB2 pulse outputs
+        via.write(&mut sys, PCR, 0b1010_1010);
+        assert_eq!(vec![true], sys.ca2_out);
+        assert_eq!(vec![true], sys.cb2_out);
+
+        via.read(&mut sys, PORTA);
+        assert_eq!(vec![true, false], sys.ca2_out);
+        via.clock_pulse(&mut sys);
+        assert_eq!(vec![true, false, true], sys.ca2_out, "The pulse should last one cycle");
+
+        via.read(&mut sys, PORTB);
+        assert_eq!(vec![true], sys.cb2_out, "Reading ORB doesn't pulse CB2");
+        via.write(&mut sys, PORTB, 0x00);
+        assert_eq!(vec![true, false], sys.cb2_out);
+        via.clock_pulse(&mut sys);
+        assert_eq!(vec![true, false, true], sys.cb2_out, "The pulse should last one cycle");
+    }
+
+    #[test]
+    fn cb_handshake_and_interrupts() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+        via.write(&mut sys, IER, 0b1001_0000);
+
+        // CB2 handshake output, CB1 positive active edge
+        via.write(&mut sys, PCR, 0b1001_0000);
+        via.write(&mut sys, PORTB, 0x42);
+        assert_eq!(vec![true, false], sys.cb2_out, "Writing ORB should set CB2 low");
+
+        sys.cb1_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(vec![true, false, true], sys.cb2_out, "CB1 active edge should set CB2 high");
+        assert_eq!(0b1001_0000, via.read(&mut sys, IFR), "IFR4 should be set");
+        assert!(sys.irq);
+
+        via.read(&mut sys, PORTB);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Reading ORB should clear IFR4");
+
+        // CB2 input, negative active edge
+        via.write(&mut sys, PCR, 0b0000_0000);
+        sys.cb2_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x00, via.read(&mut sys, IFR));
+        sys.cb2_in = false;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0b0000_1000, via.read(&mut sys, IFR), "IFR3 should be set");
+        via.write(&mut sys, PORTB, 0x00);
+        assert_eq!(0x00, via.read(&mut sys, IFR), "Writing ORB should clear IFR3");
+    }
+
+    #[test]
+    fn pa_latching() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // PA latching enabled, CA1 negative active edge
+        via.write(&mut sys, ACR, 0b0000_0001);
+        sys.ca1_in = true;
+        sys.pa = 0x12;
+        via.clock_pulse(&mut sys);
+
+        sys.ca1_in = false;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x12, via.read(&mut sys, PORTA), "IRA should hold PA from the CA1 edge");
+
+        sys.pa = 0x34;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x12, via.read(&mut sys, PORTA), "IRA shouldn't change without a CA1 edge");
+
+        sys.ca1_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x12, via.read(&mut sys, PORTA), "Only the active edge latches PA");
+
+        sys.ca1_in = false;
+        via.clock_pulse(&mut sys);
+        sys.pa = 0x56;
+        assert_eq!(0x34, via.read(&mut sys, PORTA), "IRA should hold PA from the last CA1 edge");
+
+        // Disabling latching reads the pins directly
+        via.write(&mut sys, ACR, 0b0000_0000);
+        assert_eq!(0x56, via.read(&mut sys, PORTA));
+    }
+
+    #[test]
+    fn pb_latching() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // PB latching enabled, CB1 positive active edge, PB7-4 as outputs
+        via.write(&mut sys, ACR, 0b0000_0010);
+        via.write(&mut sys, PCR, 0b0001_0000);
+        via.write(&mut sys, DDRB, 0xf0);
+        via.write(&mut sys, PORTB, 0xa0);
+        sys.pb = (sys.pb & 0xf0) | 0x05;
+
+        sys.cb1_in = true;
+        via.clock_pulse(&mut sys);
+        sys.pb = (sys.pb & 0xf0) | 0x0c;
+        assert_eq!(0xa5, via.read(&mut sys, PORTB), "Inputs should come from IRB, outputs from ORB");
+
+        via.write(&mut sys, PORTB, 0x30);
+        assert_eq!(0x35, via.read(&mut sys, PORTB), "Outputs always read ORB");
+
+        sys.cb1_in = false;
+        via.clock_pulse(&mut sys);
+        sys.cb1_in = true;
+        via.clock_pulse(&mut sys);
+        assert_eq!(0x3c, via.read(&mut sys, PORTB), "IRB should hold PB from the last CB1 edge");
+    }
+
+    enum Access {
+        Read(u8),
+        Write(u8, u8),
+    }
+
+    struct FlagCase {
+        access: Access,
+        // Flags set in IFR before the access, and the ones expected after it
+        ifr_before: u8,
+        ifr_after: u8,
+    }
+
+    #[test]
+    fn register_flag_side_effects() {
+        use Access::*;
+
+        let cases = [
+            // Port accesses clear the control line flags
+            FlagCase { access: Read(PORTB), ifr_before: 0b0111_1111, ifr_after: 0b0110_0111 },
+            FlagCase { access: Write(PORTB, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0110_0111 },
+            FlagCase { access: Read(PORTA), ifr_before: 0b0111_1111, ifr_after: 0b0111_1100 },
+            FlagCase { access: Write(PORTA, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1100 },
+            FlagCase { access: Read(PA_NO_HS), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(PA_NO_HS, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(DDRB), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(DDRB, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(DDRA), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(DDRA, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            // T1
+            FlagCase { access: Read(T1C_L), ifr_before: 0b0111_1111, ifr_after: 0b0011_1111 },
+            FlagCase { access: Write(T1C_L, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(T1C_H), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(T1C_H, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0011_1111 },
+            FlagCase { access: Read(T1L_L), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(T1L_L, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(T1L_H), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(T1L_H, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0011_1111 },
+            // T2
+            FlagCase { access: Read(T2C_L), ifr_before: 0b0111_1111, ifr_after: 0b0101_1111 },
+            FlagCase { access: Write(T2C_L, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(T2C_H), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(T2C_H, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0101_1111 },
+            // Shift register
+            FlagCase { access: Read(SR), ifr_before: 0b0111_1111, ifr_after: 0b0111_1011 },
+            FlagCase { access: Write(SR, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1011 },
+            // Control registers
+            FlagCase { access: Read(ACR), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(ACR, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(PCR), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(PCR, 0x00), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Read(IER), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(IER, 0x7f), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            // Writing a 1 to an IFR bit clears it, bit 7 is ignored
+            FlagCase { access: Read(IFR), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(IFR, 0b1000_0000), ifr_before: 0b0111_1111, ifr_after: 0b0111_1111 },
+            FlagCase { access: Write(IFR, 0b0101_0101), ifr_before: 0b0111_1111, ifr_after: 0b0010_1010 },
+        ];
+
+        for (i, case) in cases.iter().enumerate() {
+            let mut sys = TestViaSystem::default();
+            let mut via = W65C22S::new();
+
+            for flag in 0..7 {
+                if (case.ifr_before >> flag) & 1 == 1 {
+                    via.change_interrupt_flag(&mut sys, Some(flag), true);
+                }
+            }
+
+            match case.access {
+                Read(register_select) => { via.read(&mut sys, register_select); },
+                Write(register_select, data) => via.write(&mut sys, register_select, data),
+            }
+
+            assert_eq!(case.ifr_after, via.ifr & 0b0111_1111, "Case {}", i);
+        }
+    }
+
+    #[test]
+    fn independent_interrupt_flags() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // CA2 and CB2 independent interrupt inputs
+        via.write(&mut sys, PCR, 0b0010_0010);
+        for flag in 0..5 {
+            via.change_interrupt_flag(&mut sys, Some(flag), true);
+        }
+        via.read(&mut sys, PORTA);
+        via.write(&mut sys, PORTB, 0x00);
+        assert_eq!(0b0000_1101, via.read(&mut sys, IFR), "Only IFR1 and IFR4 should be cleared");
+    }
+
+    struct ValueCase {
+        writes: &'static [(u8, u8)],
+        register_select: u8,
+        expected: u8,
+    }
+
+    #[test]
+    fn register_values() {
+        let cases = [
+            ValueCase { writes: &[(DDRB, 0x5a)], register_select: DDRB, expected: 0x5a },
+            ValueCase { writes: &[(DDRA, 0xa5)], register_select: DDRA, expected: 0xa5 },
+            // PB outputs read ORB, PB inputs read the pins (0x0f on TestViaSystem)
+            ValueCase { writes: &[(DDRB, 0xf0), (PORTB, 0x3c)], register_select: PORTB, expected: 0x3f },
+            // PA always reads the pins, even the outputs
+            ValueCase { writes: &[(DDRA, 0xf0), (PORTA, 0x3c)], register_select: PORTA, expected: 0x3c },
+            ValueCase { writes: &[(DDRA, 0xf0), (PA_NO_HS, 0x3c)], register_select: PA_NO_HS, expected: 0x3c },
+            // T1 latches
+            ValueCase { writes: &[(T1C_L, 0x34)], register_select: T1L_L, expected: 0x34 },
+            ValueCase { writes: &[(T1L_L, 0x34)], register_select: T1L_L, expected: 0x34 },
+            ValueCase { writes: &[(T1L_H, 0x12)], register_select: T1L_H, expected: 0x12 },
+            ValueCase { writes: &[(T1C_H, 0x12)], register_select: T1L_H, expected: 0x12 },
+            // Writing T1C_H transfers the latches into the counter
+            ValueCase { writes: &[(T1L_L, 0x34), (T1C_H, 0x12)], register_select: T1C_L, expected: 0x34 },
+            ValueCase { writes: &[(T1L_L, 0x34), (T1C_H, 0x12)], register_select: T1C_H, expected: 0x12 },
+            // Writing T1L_H doesn't touch the counter
+            ValueCase { writes: &[(T1C_L, 0x00), (T1C_H, 0x00), (T1L_H, 0x12)], register_select: T1C_H, expected: 0x00 },
+            // Writing T2C_H transfers the low latch into the counter
+            ValueCase { writes: &[(T2C_L, 0x78), (T2C_H, 0x56)], register_select: T2C_L, expected: 0x78 },
+            ValueCase { writes: &[(T2C_L, 0x78), (T2C_H, 0x56)], register_select: T2C_H, expected: 0x56 },
+            ValueCase { writes: &[(SR, 0x81)], register_select: SR, expected: 0x81 },
+            ValueCase { writes: &[(ACR, 0xc3)], register_select: ACR, expected: 0xc3 },
+            ValueCase { writes: &[(PCR, 0x11)], register_select: PCR, expected: 0x11 },
+            // IER bit 7 always reads as 1
+            ValueCase { writes: &[], register_select: IER, expected: 0x80 },
+            ValueCase { writes: &[(IER, 0b1000_0101)], register_select: IER, expected: 0b1000_0101 },
+            ValueCase { writes: &[(IER, 0b1111_1111), (IER, 0b0000_0101)], register_select: IER, expected: 0b1111_1010 },
+            // Writing 0 to IER bit 7 with no other bit set changes nothing
+            ValueCase { writes: &[(IER, 0b1000_0001), (IER, 0b0000_0000)], register_select: IER, expected: 0b1000_0001 },
+        ];
+
+        for (i, case) in cases.iter().enumerate() {
+            let mut sys = TestViaSystem { pa: 0x3c, pb: 0x0f, ..Default::default() };
+            let mut via = W65C22S::new();
+
+            for &(register_select, data) in case.writes {
+                via.write(&mut sys, register_select, data);
+            }
+            assert_eq!(case.expected, via.read(&mut sys, case.register_select), "Case {}", i);
+        }
+    }
+
+    #[test]
+    fn pa_no_handshake() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        // CA2 handshake output
+        via.write(&mut sys, PCR, 0b0000_1000);
+        via.read(&mut sys, PA_NO_HS);
+        via.write(&mut sys, PA_NO_HS, 0x00);
+        assert_eq!(vec![true], sys.ca2_out, "Register 0xF shouldn't trigger the handshake");
+    }
+
+    #[test]
+    #[should_panic]
+    fn illegal_register_select() {
+        let mut sys = TestViaSystem::default();
+        let mut via = W65C22S::new();
+
+        via.read(&mut sys, 0x10);
+    }
 }
@@ -0,0 +1,337 @@
+use std::fmt;
+use super::inputs::INPUT_COUNT;
+
+/// A pin of a 6522 that can be connected to a peripheral.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ViaPin {
+    // u8 is the bit position, with 0 <= u8 <= 7
+    PA(u8),
+    PB(u8),
+    CA1,
+    CA2,
+    CB1,
+    CB2,
+}
+
+// Number of ViaPin values, used to size the per-VIA net tables
+pub const VIA_PIN_COUNT: usize = 20;
+
+impl ViaPin {
+    /// Position of the pin in a per-VIA net table.
+    pub fn index(self) -> usize {
+        match self {
+            ViaPin::PA(bit) => bit as usize,
+            ViaPin::PB(bit) => 8 + bit as usize,
+            ViaPin::CA1 => 16,
+            ViaPin::CA2 => 17,
+            ViaPin::CB1 => 18,
+            ViaPin::CB2 => 19,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<ViaPin> {
+        let bit = |port_bit: &str| match port_bit.parse::<u8>() {
+            Ok(bit) if bit <= 7 => Some(bit),
+            _ => None,
+        };
+
+        match name.to_ascii_uppercase().as_str() {
+            "CA1" => Some(ViaPin::CA1),
+            "CA2" => Some(ViaPin::CA2),
+            "CB1" => Some(ViaPin::CB1),
+            "CB2" => Some(ViaPin::CB2),
+            name if name.starts_with("PA") => bit(&name[2..]).map(ViaPin::PA),
+            name if name.starts_with("PB") => bit(&name[2..]).map(ViaPin::PB),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ViaPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViaPin::PA(bit) => write!(f, "PA{}", bit),
+            ViaPin::PB(bit) => write!(f, "PB{}", bit),
+            ViaPin::CA1 => write!(f, "CA1"),
+            ViaPin::CA2 => write!(f, "CA2"),
+            ViaPin::CB1 => write!(f, "CB1"),
+            ViaPin::CB2 => write!(f, "CB2"),
+        }
+    }
+}
+
+/// A pin of the HD44780 LCD controller.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LcdPin {
+    Register,
+    ReadWrite,
+    Enable,
+    // u8 is the data bit position, with 0 <= u8 <= 7
+    Data(u8),
+}
+
+/// A contact of the input devices, see `Inputs`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputPin {
+    // u8 is the number of the DIP switch or push-button, with 0 <= u8 < INPUT_COUNT
+    Switch(u8),
+    Button(u8),
+    EncoderA,
+    EncoderB,
+    KeyboardClock,
+    KeyboardData,
+}
+
+/// A pin of a peripheral that can be connected to a VIA pin.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PeriphPin {
+    Lcd(LcdPin),
+    Input(InputPin),
+}
+
+impl PeriphPin {
+    fn parse(name: &str) -> Option<PeriphPin> {
+        let input = |nb: &str| match nb.parse::<u8>() {
+            Ok(nb) if (nb as usize) < INPUT_COUNT => Some(nb),
+            _ => None,
+        };
+
+        match name.to_ascii_uppercase().as_str() {
+            "LCD.RS" => Some(PeriphPin::Lcd(LcdPin::Register)),
+            "LCD.RW" => Some(PeriphPin::Lcd(LcdPin::ReadWrite)),
+            "LCD.E" => Some(PeriphPin::Lcd(LcdPin::Enable)),
+            name if name.starts_with("LCD.D") => match name[5..].parse::<u8>() {
+                Ok(bit) if bit <= 7 => Some(PeriphPin::Lcd(LcdPin::Data(bit))),
+                _ => None,
+            },
+            "ENC.A" => Some(PeriphPin::Input(InputPin::EncoderA)),
+            "ENC.B" => Some(PeriphPin::Input(InputPin::EncoderB)),
+            "KBD.CLK" => Some(PeriphPin::Input(InputPin::KeyboardClock)),
+            "KBD.DATA" => Some(PeriphPin::Input(InputPin::KeyboardData)),
+            name if name.starts_with("SW.") => input(&name[3..]).map(|nb| PeriphPin::Input(InputPin::Switch(nb))),
+            name if name.starts_with("BTN.") => input(&name[4..]).map(|nb| PeriphPin::Input(InputPin::Button(nb))),
+            _ => None,
+        }
+    }
+}
+
+/// What a net settles to when nothing drives it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pull {
+    Up,
+    Down,
+    // Reads random values, like a real unconnected CMOS input
+    Floating,
+}
+
+/// The wire between one VIA pin and the peripheral pins connected to it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Net {
+    pub periph_pins: Vec<PeriphPin>,
+    pub pull: Pull,
+}
+
+impl Net {
+    fn unconnected() -> Net {
+        Net {
+            periph_pins: Vec::new(),
+            pull: Pull::Floating,
+        }
+    }
+}
+
+/// How the pins of every VIA are connected, usually loaded from a wiring file.
+///
+/// The file is made of sections starting with the address of a VIA between brackets,
+/// followed by one line per VIA pin: the pin name, the peripheral pins connected to it,
+/// and optionally `pullup` or `pulldown`. Everything after a `#` is a comment.
+/// ```text
+/// [6000]
+/// PB0 LCD.D4
+/// CA1 pullup
+/// ```
+/// Pins not listed are left floating.
+///
+/// The peripheral pins are `LCD.RS`, `LCD.RW`, `LCD.E` and `LCD.D0` to `LCD.D7` for the LCD,
+/// and for the input devices `SW.0` to `SW.7` (DIP switches), `BTN.0` to `BTN.7` (push-buttons)
+/// `ENC.A`, `ENC.B` (rotary encoder) and `KBD.CLK`, `KBD.DATA` (PS/2 keyboard, usually with
+/// the clock on CA1). The input devices connect their pin to the ground when closed
+/// or pulling it low, so they need a pull-up.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Wiring {
+    // (VIA base address, net of each pin indexed by ViaPin::index)
+    vias: Vec<(u16, Vec<Net>)>,
+}
+
+impl Wiring {
+    /// The breadboard the emulator was written for: the LCD in 4-bit mode on port B,
+    /// LEDs on port A, and pull-up resistors on the control lines.
+    pub fn default_for(via_addr: u16) -> Wiring {
+        Wiring::parse(&format!("
+            [{:04x}]
+            PB0 LCD.D4
+            PB1 LCD.D5
+            PB2 LCD.D6
+            PB3 LCD.D7
+            PB5 LCD.E
+            PB6 LCD.RW
+            PB7 LCD.RS
+            # The control lines are read on every cycle to detect edges,
+            # so they are held high instead of floating, to avoid triggering interrupts at random
+            CA1 pullup
+            CA2 pullup
+            CB1 pullup
+            CB2 pullup
+        ", via_addr)).expect("Invalid default wiring")
+    }
+
+    pub fn parse(text: &str) -> Result<Wiring, String> {
+        let mut wiring = Wiring { vias: Vec::new() };
+
+        for (line_nb, line) in text.lines().enumerate() {
+            let err = |msg: String| Err(format!("line {}: {}", line_nb + 1, msg));
+
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                let addr = line[1..line.len() - 1].trim().trim_start_matches('$');
+                let addr = match u16::from_str_radix(addr, 16) {
+                    Ok(addr) => addr,
+                    Err(_) => return err(format!("invalid VIA address \"{}\"", addr)),
+                };
+                if wiring.vias.iter().any(|(via_addr, _)| *via_addr == addr) {
+                    return err(format!("VIA {:04x} is wired twice", addr));
+                }
+                wiring.vias.push((addr, vec![Net::unconnected(); VIA_PIN_COUNT]));
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let pin_name = words.next().unwrap();
+            let via_pin = match ViaPin::parse(pin_name) {
+                Some(via_pin) => via_pin,
+                None => return err(format!("unknown VIA pin \"{}\"", pin_name)),
+            };
+            let nets = match wiring.vias.last_mut() {
+                Some((_, nets)) => nets,
+                None => return err(String::from("pin outside of a [VIA address] section")),
+            };
+            if nets[via_pin.index()] != Net::unconnected() {
+                return err(format!("{} is wired twice", via_pin));
+            }
+
+            let mut net = Net::unconnected();
+            for word in words {
+                match word.to_ascii_lowercase().as_str() {
+                    "pullup" => net.pull = Pull::Up,
+                    "pulldown" => net.pull = Pull::Down,
+                    _ => match PeriphPin::parse(word) {
+                        Some(periph_pin) => net.periph_pins.push(periph_pin),
+                        None => return err(format!("unknown peripheral pin \"{}\"", word)),
+                    },
+                }
+            }
+            nets[via_pin.index()] = net;
+        }
+
+        Ok(wiring)
+    }
+
+    /// Addresses of the VIAs that have a section in the wiring.
+    pub fn via_addrs(&self) -> impl Iterator<Item = u16> + '_ {
+        self.vias.iter().map(|(addr, _)| *addr)
+    }
+
+    /// Nets of the VIA mapped to `via_addr`, indexed by `ViaPin::index`.
+    /// A VIA without a section in the wiring has all its pins floating.
+    pub fn nets_of(&self, via_addr: u16) -> Vec<Net> {
+        self.vias.iter()
+            .find(|(addr, _)| *addr == via_addr)
+            .map(|(_, nets)| nets.clone())
+            .unwrap_or_else(|| vec![Net::unconnected(); VIA_PIN_COUNT])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_wiring() {
+        let wiring = Wiring::default_for(0x6000);
+        let nets = wiring.nets_of(0x6000);
+
+        assert_eq!(vec![PeriphPin::Lcd(LcdPin::Data(4))], nets[ViaPin::PB(0).index()].periph_pins);
+        assert_eq!(vec![PeriphPin::Lcd(LcdPin::Register)], nets[ViaPin::PB(7).index()].periph_pins);
+        assert_eq!(Net::unconnected(), nets[ViaPin::PB(4).index()]);
+        assert_eq!(Pull::Up, nets[ViaPin::CA1.index()].pull);
+        assert_eq!(Pull::Floating, nets[ViaPin::PA(0).index()].pull);
+
+        assert!(wiring.nets_of(0x5000).iter().all(|net| *net == Net::unconnected()));
+    }
+
+    #[test]
+    fn parse_wiring() {
+        let wiring = Wiring::parse("
+            # Two VIAs
+            [6000]
+            pb7 lcd.rs    # case doesn't matter
+            PA3 pulldown
+            [$5000]
+            CB2 LCD.D0 LCD.D1 pullup
+        ").unwrap();
+
+        assert_eq!(vec![0x6000, 0x5000], wiring.via_addrs().collect::<Vec<_>>());
+        assert_eq!(vec![PeriphPin::Lcd(LcdPin::Register)], wiring.nets_of(0x6000)[ViaPin::PB(7).index()].periph_pins);
+        assert_eq!(Pull::Down, wiring.nets_of(0x6000)[ViaPin::PA(3).index()].pull);
+        assert_eq!(Net {
+            periph_pins: vec![PeriphPin::Lcd(LcdPin::Data(0)), PeriphPin::Lcd(LcdPin::Data(1))],
+            pull: Pull::Up,
+        }, wiring.nets_of(0x5000)[ViaPin::CB2.index()]);
+    }
+
+    #[test]
+    fn parse_inputs() {
+        let wiring = Wiring::parse("
+            [6000]
+            PA0 SW.0 pullup
+            PA7 btn.7 pullup
+            CA1 ENC.A pullup
+            PA1 enc.b pullup
+            CB1 KBD.CLK pullup
+            PB0 kbd.data pullup
+        ").unwrap();
+        let nets = wiring.nets_of(0x6000);
+
+        assert_eq!(vec![PeriphPin::Input(InputPin::Switch(0))], nets[ViaPin::PA(0).index()].periph_pins);
+        assert_eq!(vec![PeriphPin::Input(InputPin::Button(7))], nets[ViaPin::PA(7).index()].periph_pins);
+        assert_eq!(vec![PeriphPin::Input(InputPin::EncoderA)], nets[ViaPin::CA1.index()].periph_pins);
+        assert_eq!(vec![PeriphPin::Input(InputPin::EncoderB)], nets[ViaPin::PA(1).index()].periph_pins);
+        assert_eq!(vec![PeriphPin::Input(InputPin::KeyboardClock)], nets[ViaPin::CB1.index()].periph_pins);
+        assert_eq!(vec![PeriphPin::Input(InputPin::KeyboardData)], nets[ViaPin::PB(0).index()].periph_pins);
+
+        assert_eq!(Err(String::from("line 2: unknown peripheral pin \"BTN.8\"")),
+            Wiring::parse("[6000]\nPA0 BTN.8"));
+        assert_eq!(Err(String::from("line 2: unknown peripheral pin \"ENC.C\"")),
+            Wiring::parse("[6000]\nPA0 ENC.C"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(String::from("line 1: pin outside of a [VIA address] section")),
+            Wiring::parse("PB0 LCD.D4"));
+        assert_eq!(Err(String::from("line 2: unknown VIA pin \"PB8\"")),
+            Wiring::parse("[6000]\nPB8 LCD.D4"));
+        assert_eq!(Err(String::from("line 2: unknown peripheral pin \"LCD.D9\"")),
+            Wiring::parse("[6000]\nPB0 LCD.D9"));
+        assert_eq!(Err(String::from("line 3: PB0 is wired twice")),
+            Wiring::parse("[6000]\nPB0 LCD.D4\nPB0 pullup"));
+        assert_eq!(Err(String::from("line 2: VIA 6000 is wired twice")),
+            Wiring::parse("[6000]\n[6000]"));
+        assert_eq!(Err(String::from("line 1: invalid VIA address \"60g0\"")),
+            Wiring::parse("[60g0]"));
+    }
+}